@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+use reqwest::StatusCode;
+use url::Url;
+
+use crate::parser::error::ParseError;
+
+/// An error talking to a CalDAV server
+#[derive(Debug)]
+pub enum CalDavError {
+    Http(reqwest::Error),
+    /// a response status that wasn't expected for the request
+    Status { url: Url, status: StatusCode },
+    /// 412: the resource changed since its ETag was read (or already exists when creating)
+    PreconditionFailed(Url),
+    /// RFC 4918 16: a 403 or 409 naming the failed condition, ex. "valid-sync-token"
+    Condition { url: Url, status: StatusCode, condition: String },
+    Xml(String),
+    Parse { url: Url, error: Box<ParseError> },
+    /// a property the server should have returned, ex. "current-user-principal"
+    MissingProperty(&'static str),
+}
+
+impl Display for CalDavError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "HTTP error: {e}"),
+            Self::Status { url, status } => write!(f, "Unexpected status {status} from {url}"),
+            Self::PreconditionFailed(url) => write!(f, "Precondition failed for {url}"),
+            Self::Condition { url, status, condition } => write!(f, "{condition} failed for {url} ({status})"),
+            Self::Xml(message) => write!(f, "Invalid XML response: {message}"),
+            Self::Parse { url, error } => write!(f, "Invalid calendar object {url}: {error}"),
+            Self::MissingProperty(name) => write!(f, "Response missing {name}"),
+        }
+    }
+}
+
+impl CalDavError {
+    /// RFC 6578 3.2: the sync-token expired, sync again without one
+    pub fn is_invalid_sync_token(&self) -> bool {
+        matches!(self, Self::Condition { condition, .. } if condition == "valid-sync-token")
+    }
+}
+
+impl std::error::Error for CalDavError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Http(e) => Some(e),
+            Self::Parse { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for CalDavError {
+    fn from(value: reqwest::Error) -> Self {
+        Self::Http(value)
+    }
+}
+
+impl From<minidom::Error> for CalDavError {
+    fn from(value: minidom::Error) -> Self {
+        Self::Xml(value.to_string())
+    }
+}
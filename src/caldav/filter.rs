@@ -0,0 +1,557 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use minidom::Element;
+
+use crate::{
+    component::{ICalComponent, VALARM, VEVENT, VFREEBUSY, VJOURNAL, VTODO},
+    property::ICalProperty,
+    query::ICalTimeRange,
+    values::ICalValue,
+};
+
+use super::{error::CalDavError, CALDAV};
+
+/// RFC 4791 9.7.1: matches components named name
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompFilter {
+    pub name: String,
+    /// matches only if there is no such component, other tests are ignored
+    pub is_not_defined: bool,
+    pub time_range: Option<ICalTimeRange>,
+    pub prop_filters: Vec<PropFilter>,
+    pub comp_filters: Vec<CompFilter>,
+}
+
+/// RFC 4791 9.7.2: matches properties named name
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropFilter {
+    pub name: String,
+    /// matches only if there is no such property, other tests are ignored
+    pub is_not_defined: bool,
+    pub time_range: Option<ICalTimeRange>,
+    pub text_match: Option<TextMatch>,
+    pub param_filters: Vec<ParamFilter>,
+}
+
+/// RFC 4791 9.7.3: matches parameters named name
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParamFilter {
+    pub name: String,
+    /// matches only if there is no such parameter, text_match is ignored
+    pub is_not_defined: bool,
+    pub text_match: Option<TextMatch>,
+}
+
+/// RFC 4791 9.7.5: a substring match
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextMatch {
+    pub text: String,
+    pub collation: Collation,
+    /// matches if text is NOT a substring
+    pub negate: bool,
+}
+
+/// RFC 4790: how text is compared
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// "i;ascii-casemap", case-insensitive for ASCII letters only
+    #[default]
+    AsciiCasemap,
+    /// "i;unicode-casemap" (RFC 5051), approximated by lowercasing (without NFKD)
+    UnicodeCasemap,
+    /// "i;octet", exact bytes
+    Octet,
+}
+
+impl CompFilter {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            is_not_defined: false,
+            time_range: None,
+            prop_filters: Vec::new(),
+            comp_filters: Vec::new(),
+        }
+    }
+
+    pub fn is_not_defined(mut self) -> Self {
+        self.is_not_defined = true;
+        self
+    }
+
+    pub fn time_range(mut self, range: ICalTimeRange) -> Self {
+        self.time_range = Some(range);
+        self
+    }
+
+    pub fn prop_filter(mut self, filter: PropFilter) -> Self {
+        self.prop_filters.push(filter);
+        self
+    }
+
+    pub fn comp_filter(mut self, filter: CompFilter) -> Self {
+        self.comp_filters.push(filter);
+        self
+    }
+
+    /// <C:comp-filter name="...">
+    pub fn to_xml(&self) -> Element {
+        let builder = Element::builder("comp-filter", CALDAV).attr("name", &*self.name);
+        if self.is_not_defined {
+            return builder.append(Element::bare("is-not-defined", CALDAV)).build();
+        }
+        builder
+            .append_all(self.time_range.as_ref().map(time_range_to_xml))
+            .append_all(self.prop_filters.iter().map(PropFilter::to_xml))
+            .append_all(self.comp_filters.iter().map(CompFilter::to_xml))
+            .build()
+    }
+
+    /// Parses a <C:filter> (or its <C:comp-filter>)
+    pub fn from_xml(element: &Element) -> Result<Self, CalDavError> {
+        let element = match element.is("filter", CALDAV) {
+            true => element.get_child("comp-filter", CALDAV).ok_or(xml_error("Filter missing comp-filter"))?,
+            false => element,
+        };
+        let mut filter = Self::new(name_attr(element)?);
+        for child in element.children().filter(|child| child.ns() == CALDAV) {
+            match child.name() {
+                "is-not-defined" => filter.is_not_defined = true,
+                "time-range" => filter.time_range = Some(time_range_from_xml(child)?),
+                "prop-filter" => filter.prop_filters.push(PropFilter::from_xml(child)?),
+                "comp-filter" => filter.comp_filters.push(CompFilter::from_xml(child)?),
+                name => return Err(xml_error(&format!("Unexpected {name} in comp-filter"))),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// RFC 4791 9.7.1: whether a calendar object (its VCALENDAR) matches this filter
+    /// Names match in any case, like RFC 5545 names (ex. "BEGIN:vevent")
+    /// NOTE: VEVENTs and VJOURNALs without a DTSTART don't match a time range, undated VTODOs do (RFC 4791 9.9)
+    pub fn matches(&self, comp: &ICalComponent) -> bool {
+        match self.is_not_defined {
+            true => !self.name.eq_ignore_ascii_case(&comp.name),
+            false => self.name.eq_ignore_ascii_case(&comp.name) && self.matches_comp(comp, &[]),
+        }
+    }
+
+    /// whether parent (the last of ancestors) has a matching sub-component
+    fn matches_in(&self, ancestors: &[&ICalComponent]) -> bool {
+        let Some(parent) = ancestors.last() else {
+            return false
+        };
+        let comps = parent.comps.get_vec(&self.name.to_ascii_uppercase()).map(Vec::as_slice).unwrap_or_default();
+        match self.is_not_defined {
+            true => comps.is_empty(),
+            false => comps.iter().any(|comp| self.matches_comp(comp, ancestors)),
+        }
+    }
+
+    fn matches_comp(&self, comp: &ICalComponent, ancestors: &[&ICalComponent]) -> bool {
+        if self.time_range.as_ref().is_some_and(|range| !comp_in_range(comp, ancestors, range)) {
+            return false
+        }
+        let path = [ancestors, &[comp]].concat();
+        self.prop_filters.iter().all(|filter| filter.matches(comp))
+            && self.comp_filters.iter().all(|filter| filter.matches_in(&path))
+    }
+}
+
+impl PropFilter {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            is_not_defined: false,
+            time_range: None,
+            text_match: None,
+            param_filters: Vec::new(),
+        }
+    }
+
+    pub fn is_not_defined(mut self) -> Self {
+        self.is_not_defined = true;
+        self
+    }
+
+    /// only for date, date-time and period values
+    pub fn time_range(mut self, range: ICalTimeRange) -> Self {
+        self.time_range = Some(range);
+        self
+    }
+
+    pub fn text_match(mut self, text_match: TextMatch) -> Self {
+        self.text_match = Some(text_match);
+        self
+    }
+
+    pub fn param_filter(mut self, filter: ParamFilter) -> Self {
+        self.param_filters.push(filter);
+        self
+    }
+
+    /// <C:prop-filter name="...">
+    pub fn to_xml(&self) -> Element {
+        let builder = Element::builder("prop-filter", CALDAV).attr("name", &*self.name);
+        if self.is_not_defined {
+            return builder.append(Element::bare("is-not-defined", CALDAV)).build();
+        }
+        builder
+            .append_all(self.time_range.as_ref().map(time_range_to_xml))
+            .append_all(self.text_match.as_ref().map(TextMatch::to_xml))
+            .append_all(self.param_filters.iter().map(ParamFilter::to_xml))
+            .build()
+    }
+
+    pub fn from_xml(element: &Element) -> Result<Self, CalDavError> {
+        let mut filter = Self::new(name_attr(element)?);
+        for child in element.children().filter(|child| child.ns() == CALDAV) {
+            match child.name() {
+                "is-not-defined" => filter.is_not_defined = true,
+                "time-range" => filter.time_range = Some(time_range_from_xml(child)?),
+                "text-match" => filter.text_match = Some(TextMatch::from_xml(child)?),
+                "param-filter" => filter.param_filters.push(ParamFilter::from_xml(child)?),
+                name => return Err(xml_error(&format!("Unexpected {name} in prop-filter"))),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// RFC 4791 9.7.2: whether comp has a matching property
+    pub fn matches(&self, comp: &ICalComponent) -> bool {
+        let props = comp.props.get_vec(&self.name.to_ascii_uppercase()).map(Vec::as_slice).unwrap_or_default();
+        match self.is_not_defined {
+            true => props.is_empty(),
+            false => props.iter().any(|prop| self.matches_prop(prop)),
+        }
+    }
+
+    fn matches_prop(&self, prop: &ICalProperty) -> bool {
+        self.time_range.as_ref().is_none_or(|range| range.matches_value(&prop.value))
+            && self.text_match.as_ref().is_none_or(|text_match| text_match.matches(&value_text(&prop.value)))
+            && self.param_filters.iter().all(|filter| filter.matches(prop))
+    }
+}
+
+impl ParamFilter {
+    pub fn new(name: &str) -> Self {
+        Self { name: name.to_string(), is_not_defined: false, text_match: None }
+    }
+
+    pub fn is_not_defined(mut self) -> Self {
+        self.is_not_defined = true;
+        self
+    }
+
+    pub fn text_match(mut self, text_match: TextMatch) -> Self {
+        self.text_match = Some(text_match);
+        self
+    }
+
+    /// <C:param-filter name="...">
+    pub fn to_xml(&self) -> Element {
+        let builder = Element::builder("param-filter", CALDAV).attr("name", &*self.name);
+        if self.is_not_defined {
+            return builder.append(Element::bare("is-not-defined", CALDAV)).build();
+        }
+        builder.append_all(self.text_match.as_ref().map(TextMatch::to_xml)).build()
+    }
+
+    pub fn from_xml(element: &Element) -> Result<Self, CalDavError> {
+        let mut filter = Self::new(name_attr(element)?);
+        for child in element.children().filter(|child| child.ns() == CALDAV) {
+            match child.name() {
+                "is-not-defined" => filter.is_not_defined = true,
+                "text-match" => filter.text_match = Some(TextMatch::from_xml(child)?),
+                name => return Err(xml_error(&format!("Unexpected {name} in param-filter"))),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// RFC 4791 9.7.3: whether prop has a matching parameter (any of its values for multi-valued ones)
+    pub fn matches(&self, prop: &ICalProperty) -> bool {
+        match (prop.params.get_values(&self.name), self.is_not_defined) {
+            (values, true) => values.is_none(),
+            (None, false) => false,
+            (Some(values), false) => self.text_match.as_ref().is_none_or(|text_match| {
+                values.iter().any(|value| text_match.matches(value))
+            }),
+        }
+    }
+}
+
+impl TextMatch {
+    pub fn new(text: &str) -> Self {
+        Self { text: text.to_string(), collation: Collation::default(), negate: false }
+    }
+
+    pub fn collation(mut self, collation: Collation) -> Self {
+        self.collation = collation;
+        self
+    }
+
+    pub fn negate(mut self) -> Self {
+        self.negate = true;
+        self
+    }
+
+    /// <C:text-match collation="..." negate-condition="...">
+    pub fn to_xml(&self) -> Element {
+        let negate = if self.negate { "yes" } else { "no" };
+        Element::builder("text-match", CALDAV)
+            .attr("collation", self.collation.as_str())
+            .attr("negate-condition", negate)
+            .append(self.text.clone())
+            .build()
+    }
+
+    pub fn from_xml(element: &Element) -> Result<Self, CalDavError> {
+        let collation = match element.attr("collation") {
+            //RFC 4791 7.5.1: the server should answer with CALDAV:supported-collation
+            Some(name) => Collation::from_name(name).ok_or(xml_error(&format!("Unsupported collation {name}")))?,
+            None => Collation::default(),
+        };
+        Ok(Self {
+            text: element.text(),
+            collation,
+            negate: element.attr("negate-condition") == Some("yes"),
+        })
+    }
+
+    /// RFC 4791 9.7.5: whether text is a substring of value (or isn't when negated)
+    pub fn matches(&self, value: &str) -> bool {
+        let found = match self.collation {
+            Collation::Octet => value.contains(&self.text),
+            Collation::AsciiCasemap => value.to_ascii_uppercase().contains(&self.text.to_ascii_uppercase()),
+            Collation::UnicodeCasemap => value.to_lowercase().contains(&self.text.to_lowercase()),
+        };
+        found != self.negate
+    }
+}
+
+impl Collation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AsciiCasemap => "i;ascii-casemap",
+            Self::UnicodeCasemap => "i;unicode-casemap",
+            Self::Octet => "i;octet",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "i;ascii-casemap" => Some(Self::AsciiCasemap),
+            "i;unicode-casemap" => Some(Self::UnicodeCasemap),
+            "i;octet" => Some(Self::Octet),
+            _ => None,
+        }
+    }
+}
+
+/// RFC 4791 9.9 for a sub-component of the last of ancestors
+fn comp_in_range(comp: &ICalComponent, ancestors: &[&ICalComponent], range: &ICalTimeRange) -> bool {
+    let name = comp.name.as_str();
+    match name {
+        VEVENT | VTODO | VJOURNAL | VFREEBUSY => {
+            comp.matches_time_range(name, &overrides(comp, ancestors.last()), range).unwrap_or(false)
+        },
+        VALARM => {
+            let [.., grandparent, parent] = ancestors else {
+                return false
+            };
+            comp.alarm_matches_time_range(parent, &overrides(parent, Some(grandparent)), range).unwrap_or(false)
+        },
+        _ => false,
+    }
+}
+
+/// overrides are siblings with the same UID, only a master has them
+fn overrides<'a>(comp: &ICalComponent, parent: Option<&&'a ICalComponent>) -> Vec<&'a ICalComponent> {
+    match (comp.get_uid_value(), parent) {
+        (Ok(uid), Some(parent)) if !comp.props.contains_key("RECURRENCE-ID") => parent.get_overrides(&comp.name, uid),
+        _ => vec![],
+    }
+}
+
+/// the text a text-match is applied to, TEXT values unescaped
+fn value_text(value: &ICalValue) -> String {
+    match value {
+        ICalValue::Text(text) => text.clone(),
+        ICalValue::TextList(texts) => texts.join(","),
+        value => value.serialize(),
+    }
+}
+
+fn name_attr(element: &Element) -> Result<&str, CalDavError> {
+    element.attr("name").ok_or(xml_error(&format!("{} missing name", element.name())))
+}
+
+fn xml_error(message: &str) -> CalDavError {
+    CalDavError::Xml(message.to_string())
+}
+
+/// <C:time-range start="..." end="..."/>
+fn time_range_from_xml(element: &Element) -> Result<ICalTimeRange, CalDavError> {
+    let parse = |name: &str| element.attr(name)
+        .map(|value| NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ")
+            .map(|dt| dt.and_utc())
+            .map_err(|e| xml_error(&format!("Invalid time-range {name} {value}: {e}"))))
+        .transpose();
+    let range = ICalTimeRange::new(parse("start")?, parse("end")?);
+    if range.start.is_none() && range.end.is_none() {
+        return Err(xml_error("time-range needs a start or end"))
+    }
+    Ok(range)
+}
+
+/// <C:time-range start="..." end="..."/>, open ends are left out
+fn time_range_to_xml(range: &ICalTimeRange) -> Element {
+    let mut element = Element::bare("time-range", CALDAV);
+    for (name, value) in [("start", range.start), ("end", range.end)] {
+        if let Some(value) = value {
+            element.set_attr(name, utc_to_ical(&value));
+        }
+    }
+    element
+}
+
+/// RFC 4791 9.9: UTC date-times, ex. "20060104T000000Z"
+pub(crate) fn utc_to_ical(value: &DateTime<Utc>) -> String {
+    value.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::{component::ICalComponent, query::ICalTimeRange};
+    use super::{Collation, CompFilter, ParamFilter, PropFilter, TextMatch};
+
+    fn utc(d: u32, h: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(2024, 1, d, h, 0, 0).unwrap())
+    }
+
+    fn vcalendar(comp: CompFilter) -> CompFilter {
+        CompFilter::new("VCALENDAR").comp_filter(comp)
+    }
+
+    #[test]
+    fn test_filter_vevent() {
+        let vcal = ICalComponent::from_ics(r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:1
+SUMMARY:Team Müller Sync
+DTSTART:20240101T090000Z
+DTEND:20240101T100000Z
+RRULE:FREQ=DAILY;COUNT=5
+ATTENDEE;PARTSTAT=ACCEPTED:mailto:jane@example.com
+ATTENDEE;PARTSTAT=DECLINED:mailto:john@example.com
+BEGIN:VALARM
+ACTION:DISPLAY
+TRIGGER:-PT15M
+END:VALARM
+END:VEVENT
+BEGIN:VEVENT
+UID:1
+RECURRENCE-ID:20240103T090000Z
+SUMMARY:Moved
+DTSTART:20240110T090000Z
+DTEND:20240110T100000Z
+END:VEVENT
+END:VCALENDAR"#).unwrap();
+        let matches = |filter: CompFilter| vcalendar(filter).matches(&vcal);
+        let vevent = || CompFilter::new("VEVENT");
+        let range = |start: u32, end: u32| ICalTimeRange::new(utc(start, 0), utc(end, 0));
+
+        assert!(matches(vevent()));
+        assert!(!matches(vevent().is_not_defined()));
+        assert!(matches(CompFilter::new("VTODO").is_not_defined()));
+        assert!(!CompFilter::new("VTODO").matches(&vcal));
+
+        //recurring instances and the moved override
+        assert!(matches(vevent().time_range(range(4, 5))));
+        assert!(!matches(vevent().time_range(range(6, 9))));
+        assert!(matches(vevent().time_range(range(10, 11))));
+        assert!(!matches(vevent().time_range(ICalTimeRange::new(utc(3, 9), utc(3, 10)))));
+
+        //triggers 15 minutes before every instance that isn't overridden
+        let alarm = |start: Option<DateTime<Utc>>, end| vevent().comp_filter(CompFilter::new("VALARM").time_range(ICalTimeRange::new(start, end)));
+        assert!(matches(alarm(Some(Utc.with_ymd_and_hms(2024, 1, 2, 8, 40, 0).unwrap()), utc(2, 9))));
+        assert!(!matches(alarm(utc(2, 9), utc(2, 10))));
+        assert!(!matches(alarm(Some(Utc.with_ymd_and_hms(2024, 1, 10, 8, 40, 0).unwrap()), utc(10, 9))));
+
+        let summary = |text_match: TextMatch| vevent().prop_filter(PropFilter::new("SUMMARY").text_match(text_match));
+        assert!(matches(summary(TextMatch::new("team"))));
+        assert!(!matches(summary(TextMatch::new("team").collation(Collation::Octet))));
+        assert!(!matches(summary(TextMatch::new("MÜLLER"))));
+        assert!(matches(summary(TextMatch::new("MÜLLER").collation(Collation::UnicodeCasemap))));
+        //the override's SUMMARY matches
+        assert!(matches(summary(TextMatch::new("sync").negate())));
+
+        let attendee = |param: ParamFilter| vevent().prop_filter(PropFilter::new("ATTENDEE")
+            .text_match(TextMatch::new("john@"))
+            .param_filter(param));
+        assert!(matches(attendee(ParamFilter::new("PARTSTAT").text_match(TextMatch::new("declined")))));
+        assert!(!matches(attendee(ParamFilter::new("PARTSTAT").text_match(TextMatch::new("ACCEPTED")))));
+        assert!(!matches(attendee(ParamFilter::new("PARTSTAT").is_not_defined())));
+        assert!(matches(attendee(ParamFilter::new("ROLE").is_not_defined())));
+
+        assert!(matches(vevent().prop_filter(PropFilter::new("DTSTART").time_range(range(10, 11)))));
+        assert!(!matches(vevent().prop_filter(PropFilter::new("DTSTART").time_range(range(2, 3)))));
+        assert!(matches(vevent().prop_filter(PropFilter::new("LOCATION").is_not_defined())));
+    }
+
+    #[test]
+    fn test_filter_vtodo() {
+        //RFC 4791 7.8.9: incomplete, not cancelled VTODOs
+        let filter = vcalendar(CompFilter::new("VTODO")
+            .prop_filter(PropFilter::new("COMPLETED").is_not_defined())
+            .prop_filter(PropFilter::new("STATUS").text_match(TextMatch::new("CANCELLED").negate())));
+        let todo = |props: &str| ICalComponent::from_ics(&format!("BEGIN:VCALENDAR\nBEGIN:VTODO\nUID:1\n{props}\nEND:VTODO\nEND:VCALENDAR")).unwrap();
+        assert!(filter.matches(&todo("STATUS:NEEDS-ACTION")));
+        assert!(!filter.matches(&todo("STATUS:CANCELLED")));
+        assert!(!filter.matches(&todo("STATUS:COMPLETED\nCOMPLETED:20240101T000000Z")));
+        //a negated text-match still needs the property
+        assert!(!filter.matches(&todo("SUMMARY:No status")));
+
+        let due = vcalendar(CompFilter::new("VTODO").time_range(ICalTimeRange::new(utc(1, 0), utc(2, 0))));
+        assert!(due.matches(&todo("DUE:20240101T120000Z")));
+        assert!(!due.matches(&todo("DUE:20240103T120000Z")));
+        //RFC 4791 9.9: a VTODO without DTSTART, DUE, COMPLETED or CREATED always overlaps
+        assert!(due.matches(&todo("SUMMARY:Undated")));
+
+        //component names are uppercased when parsed
+        let lowercase = ICalComponent::from_ics("BEGIN:vcalendar\nBEGIN:vtodo\nUID:1\nDUE:20240101T120000Z\nEND:vtodo\nEND:vcalendar").unwrap();
+        assert!(due.matches(&lowercase));
+        assert!(vcalendar(CompFilter::new("vTodo").prop_filter(PropFilter::new("uid"))).matches(&lowercase));
+        assert!(!vcalendar(CompFilter::new("VTODO").is_not_defined()).matches(&lowercase));
+    }
+
+    #[test]
+    fn test_filter_xml() {
+        let xml = r#"<C:filter xmlns:C="urn:ietf:params:xml:ns:caldav">
+  <C:comp-filter name="VCALENDAR">
+    <C:comp-filter name="VEVENT">
+      <C:time-range start="20240101T000000Z"/>
+      <C:prop-filter name="ATTENDEE">
+        <C:text-match collation="i;octet" negate-condition="yes">mailto:jane@example.com</C:text-match>
+        <C:param-filter name="PARTSTAT"><C:is-not-defined/></C:param-filter>
+      </C:prop-filter>
+    </C:comp-filter>
+  </C:comp-filter>
+</C:filter>"#;
+        let filter = CompFilter::from_xml(&xml.parse().unwrap()).unwrap();
+        let expected = vcalendar(CompFilter::new("VEVENT")
+            .time_range(ICalTimeRange::new(utc(1, 0), None))
+            .prop_filter(PropFilter::new("ATTENDEE")
+                .text_match(TextMatch::new("mailto:jane@example.com").collation(Collation::Octet).negate())
+                .param_filter(ParamFilter::new("PARTSTAT").is_not_defined())));
+        assert_eq!(filter, expected);
+        assert_eq!(CompFilter::from_xml(&filter.to_xml()).unwrap(), filter);
+
+        let unsupported = r#"<C:prop-filter xmlns:C="urn:ietf:params:xml:ns:caldav" name="SUMMARY"><C:text-match collation="i;basic">a</C:text-match></C:prop-filter>"#;
+        assert!(PropFilter::from_xml(&unsupported.parse().unwrap()).is_err());
+        let open = r#"<C:comp-filter xmlns:C="urn:ietf:params:xml:ns:caldav" name="VEVENT"><C:time-range/></C:comp-filter>"#;
+        assert!(CompFilter::from_xml(&open.parse().unwrap()).is_err());
+    }
+}
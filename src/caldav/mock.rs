@@ -0,0 +1,98 @@
+use std::{future::Future, io::{BufRead, BufReader, Read, Write}, net::{TcpListener, TcpStream}, sync::{Arc, Mutex}, thread};
+
+use url::Url;
+
+/// A request received by [MockServer]
+#[derive(Clone, Debug)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: &str) -> Self {
+        Self { status, headers: Vec::new(), body: body.to_string() }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A local HTTP/1.1 server answering each connection with handler
+pub struct MockServer {
+    pub url: Url,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+impl MockServer {
+    pub fn start(handler: impl Fn(&MockRequest) -> MockResponse + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else { break };
+                respond(stream, &handler, &received);
+            }
+        });
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn respond(stream: TcpStream, handler: &impl Fn(&MockRequest) -> MockResponse, received: &Mutex<Vec<MockRequest>>) -> Option<()> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = (parts.next()?.to_string(), parts.next()?.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let Some((name, value)) = line.trim_end().split_once(':') else { break };
+        headers.push((name.to_string(), value.trim().to_string()));
+    }
+    let mut request = MockRequest { method, path, headers, body: String::new() };
+    let length = request.header("Content-Length").and_then(|length| length.parse().ok()).unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    request.body = String::from_utf8(body).ok()?;
+
+    let response = handler(&request);
+    //recorded before the client sees the response
+    received.lock().unwrap().push(request);
+    let mut head = format!("HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n", response.status, response.body.len());
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+    let mut stream = reader.into_inner();
+    stream.write_all(head.as_bytes()).ok()?;
+    stream.write_all(response.body.as_bytes()).ok()
+}
+
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future)
+}
@@ -0,0 +1,129 @@
+use minidom::Element;
+use url::Url;
+
+use super::{error::CalDavError, DAV};
+
+/// RFC 4918 14.24: one <response> of a 207 Multi-Status
+#[derive(Clone, Debug)]
+pub struct DavResponse {
+    pub href: Url,
+    /// the status of the whole response, ex. 404 for a member removed since a sync-token
+    pub status: Option<u16>,
+    /// properties from every successful <propstat>
+    props: Vec<Element>,
+}
+
+impl DavResponse {
+    /// returns the property named name in namespace
+    pub fn prop(&self, name: &str, namespace: &str) -> Option<&Element> {
+        self.props.iter().find(|prop| prop.is(name, namespace))
+    }
+
+    /// the text of a property, None when missing or empty
+    pub fn prop_text(&self, name: &str, namespace: &str) -> Option<String> {
+        Some(self.prop(name, namespace)?.text()).filter(|text| !text.is_empty())
+    }
+
+    /// the first DAV:href inside a property, resolved against the response's href
+    pub fn prop_href(&self, name: &str, namespace: &str) -> Option<Url> {
+        let href = self.prop(name, namespace)?.get_child("href", DAV)?.text();
+        self.href.join(href.trim()).ok()
+    }
+}
+
+/// the <multistatus> body of a 207 response, hrefs resolved against url
+pub(crate) struct MultiStatus {
+    pub responses: Vec<DavResponse>,
+    /// RFC 6578 3.2: the token to send for the next sync-collection
+    pub sync_token: Option<String>,
+}
+
+impl MultiStatus {
+    pub fn parse(body: &str, url: &Url) -> Result<Self, CalDavError> {
+        let root: Element = body.parse()?;
+        if !root.is("multistatus", DAV) {
+            return Err(CalDavError::Xml(format!("Expected multistatus, got {}", root.name())))
+        }
+        let mut responses = Vec::new();
+        for response in root.children().filter(|child| child.is("response", DAV)) {
+            let href = response.get_child("href", DAV)
+                .ok_or(CalDavError::Xml("Response missing href".to_string()))?
+                .text();
+            let href = url.join(href.trim()).map_err(|e| CalDavError::Xml(format!("Invalid href {href}: {e}")))?;
+            let status = response.get_child("status", DAV).and_then(|status| parse_status(&status.text()));
+            let props = response.children()
+                .filter(|propstat| propstat.is("propstat", DAV))
+                .filter(|propstat| {
+                    let status = propstat.get_child("status", DAV).and_then(|status| parse_status(&status.text()));
+                    status.is_none_or(|status| (200..300).contains(&status))
+                })
+                .filter_map(|propstat| propstat.get_child("prop", DAV))
+                .flat_map(|prop| prop.children().cloned())
+                .collect();
+            responses.push(DavResponse { href, status, props });
+        }
+        let sync_token = root.get_child("sync-token", DAV).map(|token| token.text().trim().to_string());
+        Ok(Self { responses, sync_token })
+    }
+}
+
+/// RFC 4918 16: the name of the condition in a <DAV:error> body, ex. "valid-sync-token"
+pub(crate) fn parse_condition(body: &str) -> Option<String> {
+    let root: Element = body.parse().ok()?;
+    if !root.is("error", DAV) {
+        return None
+    }
+    root.children().next().map(|condition| condition.name().to_string())
+}
+
+/// "HTTP/1.1 200 OK" to 200
+fn parse_status(status: &str) -> Option<u16> {
+    status.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use url::Url;
+
+    use crate::caldav::{DAV, CALDAV};
+    use super::{parse_condition, MultiStatus};
+
+    #[test]
+    fn test_multistatus() {
+        let body = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/calendars/jane/work/</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:displayname>Work</d:displayname>
+        <c:calendar-home-set><d:href>../</d:href></c:calendar-home-set>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+    <d:propstat>
+      <d:prop><d:getetag/></d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/calendars/jane/work/gone.ics</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+  <d:sync-token>http://example.com/sync/2</d:sync-token>
+</d:multistatus>"#;
+        let url = Url::parse("https://example.com/dav/").unwrap();
+        let multistatus = MultiStatus::parse(body, &url).unwrap();
+
+        let [work, gone] = multistatus.responses.as_slice() else { panic!("Expected 2 responses") };
+        assert_eq!(work.href.as_str(), "https://example.com/dav/calendars/jane/work/");
+        assert_eq!(work.prop_text("displayname", DAV).as_deref(), Some("Work"));
+        assert_eq!(work.prop_href("calendar-home-set", CALDAV).unwrap().as_str(), "https://example.com/dav/calendars/jane/");
+        assert!(work.prop("getetag", DAV).is_none());
+        assert_eq!(gone.status, Some(404));
+        assert_eq!(multistatus.sync_token.as_deref(), Some("http://example.com/sync/2"));
+
+        assert!(MultiStatus::parse("<error xmlns='DAV:'/>", &url).is_err());
+        assert_eq!(parse_condition("<d:error xmlns:d='DAV:'><d:valid-sync-token/></d:error>").as_deref(), Some("valid-sync-token"));
+    }
+}
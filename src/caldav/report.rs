@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use minidom::Element;
+use url::Url;
+
+use super::{filter::{utc_to_ical, CompFilter}, CALDAV, DAV};
+
+/// RFC 4791 7.8: the objects of a calendar matching a filter
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarQuery {
+    /// must be named VCALENDAR
+    pub filter: CompFilter,
+    pub expand: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    pub limit_recurrence_set: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+/// RFC 4791 7.9: specific objects of a calendar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CalendarMultiget {
+    pub hrefs: Vec<Url>,
+}
+
+/// RFC 6578 3.2: the members of a collection changed since a sync-token
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SyncCollection {
+    /// None for the initial sync (every member)
+    pub sync_token: Option<String>,
+    /// the most results the server should return, see [super::CalDavSync::truncated]
+    pub limit: Option<u32>,
+}
+
+impl CalendarQuery {
+    pub fn new(filter: CompFilter) -> Self {
+        Self { filter, expand: None, limit_recurrence_set: None }
+    }
+
+    /// RFC 4791 9.6.5: return recurring objects as their instances overlapping start..end,
+    /// each without RRULE, RDATE and EXDATE and in UTC
+    pub fn expand(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.expand = Some((start, end));
+        self
+    }
+
+    /// RFC 4791 9.6.6: return only the overrides (RECURRENCE-ID) that overlap start..end
+    pub fn limit_recurrence_set(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.limit_recurrence_set = Some((start, end));
+        self
+    }
+
+    /// <C:calendar-query>
+    pub fn to_xml(&self) -> Element {
+        let mut calendar_data = Element::bare("calendar-data", CALDAV);
+        for (name, range) in [("expand", self.expand), ("limit-recurrence-set", self.limit_recurrence_set)] {
+            if let Some((start, end)) = range {
+                calendar_data.append_child(Element::builder(name, CALDAV)
+                    .attr("start", utc_to_ical(&start))
+                    .attr("end", utc_to_ical(&end))
+                    .build());
+            }
+        }
+        Element::builder("calendar-query", CALDAV)
+            .append(object_props(calendar_data))
+            .append(Element::builder("filter", CALDAV).append(self.filter.to_xml()).build())
+            .build()
+    }
+}
+
+impl CalendarMultiget {
+    pub fn new(hrefs: impl IntoIterator<Item = Url>) -> Self {
+        Self { hrefs: hrefs.into_iter().collect() }
+    }
+
+    /// <C:calendar-multiget>
+    pub fn to_xml(&self) -> Element {
+        Element::builder("calendar-multiget", CALDAV)
+            .append(object_props(Element::bare("calendar-data", CALDAV)))
+            .append_all(self.hrefs.iter().map(|href| Element::builder("href", DAV).append(href.path()).build()))
+            .build()
+    }
+}
+
+impl SyncCollection {
+    pub fn new(sync_token: Option<&str>) -> Self {
+        Self { sync_token: sync_token.map(str::to_string), limit: None }
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// <D:sync-collection>, asking only for ETags (fetch changes with [CalendarMultiget])
+    pub fn to_xml(&self) -> Element {
+        let limit = self.limit.map(|limit| Element::builder("limit", DAV)
+            .append(Element::builder("nresults", DAV).append(limit.to_string()).build())
+            .build());
+        Element::builder("sync-collection", DAV)
+            .append(Element::builder("sync-token", DAV).append(self.sync_token.clone().unwrap_or_default()).build())
+            .append(Element::builder("sync-level", DAV).append("1").build())
+            .append_all(limit)
+            .append(Element::builder("prop", DAV).append(Element::bare("getetag", DAV)).build())
+            .build()
+    }
+}
+
+/// <D:prop><D:getetag/><C:calendar-data/></D:prop>
+fn object_props(calendar_data: Element) -> Element {
+    Element::builder("prop", DAV)
+        .append(Element::bare("getetag", DAV))
+        .append(calendar_data)
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use url::Url;
+
+    use crate::{caldav::filter::{Collation, CompFilter, ParamFilter, PropFilter, TextMatch}, query::ICalTimeRange};
+    use super::{CalendarMultiget, CalendarQuery, SyncCollection};
+
+    fn xml(element: minidom::Element) -> String {
+        let mut xml = Vec::new();
+        element.write_to(&mut xml).unwrap();
+        String::from_utf8(xml).unwrap()
+    }
+
+    #[test]
+    fn test_report_xml() {
+        let start = Utc.with_ymd_and_hms(2006, 1, 4, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2006, 1, 5, 0, 0, 0).unwrap();
+        let filter = CompFilter::new("VCALENDAR").comp_filter(CompFilter::new("VEVENT")
+            .time_range(ICalTimeRange::new(Some(start), None))
+            .prop_filter(PropFilter::new("ATTENDEE")
+                .text_match(TextMatch::new("mailto:jane@example.com").collation(Collation::Octet))
+                .param_filter(ParamFilter::new("PARTSTAT").text_match(TextMatch::new("DECLINED").negate())))
+            .prop_filter(PropFilter::new("X-PRIVATE").is_not_defined()));
+        let query = xml(CalendarQuery::new(filter).expand(start, end).to_xml());
+        for expected in [
+            "<calendar-query xmlns='urn:ietf:params:xml:ns:caldav'><prop xmlns='DAV:'><getetag/>",
+            "<calendar-data xmlns='urn:ietf:params:xml:ns:caldav'><expand end=\"20060105T000000Z\" start=\"20060104T000000Z\"/></calendar-data>",
+            "<filter><comp-filter name=\"VCALENDAR\"><comp-filter name=\"VEVENT\"><time-range start=\"20060104T000000Z\"/>",
+            "<prop-filter name=\"ATTENDEE\"><text-match collation=\"i;octet\" negate-condition=\"no\">mailto:jane@example.com</text-match>",
+            "<param-filter name=\"PARTSTAT\"><text-match collation=\"i;ascii-casemap\" negate-condition=\"yes\">DECLINED</text-match></param-filter>",
+            "<prop-filter name=\"X-PRIVATE\"><is-not-defined/></prop-filter>",
+        ] {
+            assert!(query.contains(expected), "{expected} missing from {query}");
+        }
+
+        let base = Url::parse("https://example.com/work/").unwrap();
+        let multiget = xml(CalendarMultiget::new([base.join("1.ics").unwrap()]).to_xml());
+        assert!(multiget.contains("<href xmlns='DAV:'>/work/1.ics</href>"), "{multiget}");
+
+        let sync = xml(SyncCollection::new(None).limit(100).to_xml());
+        assert!(sync.contains("<sync-token></sync-token><sync-level>1</sync-level><limit><nresults>100</nresults></limit>"), "{sync}");
+        let sync = xml(SyncCollection::new(Some("sync-1")).to_xml());
+        assert!(sync.contains("<sync-token>sync-1</sync-token>"), "{sync}");
+    }
+}
@@ -1,30 +1,93 @@
 use std::mem;
-use multimap::MultiMap;
 
 use crate::property::ICalProperty;
 use crate::values::ICalValue;
 
+pub mod map;
+
+use map::ICalMap;
+
 pub struct ICalComponent {
+    /// ex. "VEVENT", kept in sync with the name it's inserted under
+    /// NOTE: an unnamed root is written as a VCALENDAR
+    pub name: String,
     pub props: ICalPropertyMap,
-    pub comps: ICalComponentMap
+    pub comps: ICalComponentMap,
+    /// malformed content lines kept as is by lenient parsing
+    pub raw_lines: Vec<String>,
+}
+
+pub type ICalPropertyMap = ICalMap<ICalProperty>;
+pub type ICalComponentMap = ICalMap<ICalComponent>;
+
+/// components are written as a list since each has its name
+#[cfg(feature = "serde")]
+impl serde::Serialize for ICalComponent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct Component<'a> {
+            name: &'a str,
+            props: &'a ICalPropertyMap,
+            comps: Vec<&'a ICalComponent>,
+            #[serde(skip_serializing_if = "<[String]>::is_empty")]
+            raw_lines: &'a [String],
+        }
+        let comp = Component {
+            name: &self.name,
+            props: &self.props,
+            comps: self.comps.iter().map(|(_, comp)| comp).collect(),
+            raw_lines: &self.raw_lines,
+        };
+        serde::Serialize::serialize(&comp, serializer)
+    }
 }
 
-pub type ICalPropertyMap = MultiMap<String, ICalProperty>;
-pub type ICalComponentMap = MultiMap<String, ICalComponent>;
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ICalComponent {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Component {
+            #[serde(default)]
+            name: String,
+            #[serde(default)]
+            props: ICalPropertyMap,
+            #[serde(default)]
+            comps: Vec<ICalComponent>,
+            #[serde(default)]
+            raw_lines: Vec<String>,
+        }
+        let Component { name, props, comps, raw_lines } = serde::Deserialize::deserialize(deserializer)?;
+        let mut comp = ICalComponent { name, props, comps: ICalComponentMap::new(), raw_lines };
+        for child in comps {
+            comp.comps.insert(child.name.clone(), child);
+        }
+        comp.resolve_timezones();
+        Ok(comp)
+    }
+}
 
 impl ICalComponent {
     pub fn new(props: ICalPropertyMap, comps: ICalComponentMap) -> Self {
-        Self { props, comps }
+        Self { name: String::new(), props, comps, raw_lines: Vec::new() }
     }
 
     pub fn empty() -> Self {
         Self::new(ICalPropertyMap::new(), ICalComponentMap::new())
     }
 
+    /// creates an empty component with name (ex. VEVENT)
+    pub fn named(name: &str) -> Self {
+        let mut comp = Self::empty();
+        comp.name = name.to_string();
+        comp
+    }
+
     pub fn build(&mut self) -> Self {
         ICalComponent {
+            name: mem::take(&mut self.name),
             props: mem::take(&mut self.props),
-            comps: mem::take(&mut self.comps)
+            comps: mem::take(&mut self.comps),
+            raw_lines: mem::take(&mut self.raw_lines),
         }
     }
 
@@ -55,7 +118,8 @@ impl ICalComponent {
     }
 
     /// adds another value under the given name
-    pub fn insert_comp(&mut self, name: &str, value: ICalComponent) -> &mut Self {
+    pub fn insert_comp(&mut self, name: &str, mut value: ICalComponent) -> &mut Self {
+        value.name = name.to_string();
         self.comps.insert(name.to_string(), value);
         self
     }
@@ -72,8 +136,9 @@ impl ICalComponent {
     }
 
     /// replaces the value at the first component under the given name
-    pub fn set_comp(&mut self, name: &str, new_comp: ICalComponent) -> &mut Self {
+    pub fn set_comp(&mut self, name: &str, mut new_comp: ICalComponent) -> &mut Self {
         if let Some(comp) = self.get_comp(name) {
+            new_comp.name = name.to_string();
             *comp = new_comp;
         }
         else {
@@ -98,17 +163,18 @@ impl ICalComponent {
     /// fails if property does not exist
     pub fn set_prop_param(&mut self, prop_name: &str, param_name: &str, new_value: String) -> &mut Self {
         if let Some(prop_ref) = self.get_prop(prop_name) {
-            prop_ref.params.insert(param_name.to_string(), new_value);
+            prop_ref.params.insert(param_name, &new_value);
         }
         self
     }
 
     /// returns the value prop's param if it exists
-    pub fn get_prop_param(&self, prop_name: &str, param_name: &str) -> Option<&String> {
+    pub fn get_prop_param(&self, prop_name: &str, param_name: &str) -> Option<&str> {
         self.props.get(prop_name)?.params.get(param_name)
     }
 }
 
+pub const VCALENDAR: &str = "VCALENDAR";
 pub const VEVENT: &str = "VEVENT";
 pub const VTODO: &str = "VTODO";
 pub const VALARM: &str = "VALARM";
@@ -119,7 +185,7 @@ pub const VTIMEZONE: &str = "VTIMEZONE";
 impl ICalComponent {
     /// creates a default VCALENDAR
     pub fn vcalendar() -> Self {
-        let mut vcal = Self::empty();
+        let mut vcal = Self::named(VCALENDAR);
         vcal.version("2.0".to_string())
             .calscale("GREGORIAN".to_string())
             .prodid("-//Liam Snow//ical-rs//EN".to_string());
@@ -130,9 +196,9 @@ impl ICalComponent {
             .vtodo(vtodo)
             .build()
     }
-    pub fn vcalendar_with_vevent(vtodo: Self) -> Self {
+    pub fn vcalendar_with_vevent(vevent: Self) -> Self {
         Self::vcalendar()
-            .vtodo(vtodo)
+            .vevent(vevent)
             .build()
     }
 
@@ -0,0 +1,183 @@
+/// A multimap that remembers the order values were inserted in,
+/// so properties and components round-trip in their original order
+#[derive(Clone, Debug)]
+pub struct ICalMap<T> {
+    groups: Vec<(String, Vec<T>)>,
+    /// group index of each inserted value, in order
+    order: Vec<usize>,
+}
+
+impl<T> Default for ICalMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ICalMap<T> {
+    pub fn new() -> Self {
+        Self { groups: Vec::new(), order: Vec::new() }
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.groups.iter().position(|(n, _)| n == name)
+    }
+
+    /// returns the first value for name
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.get_vec(name)?.first()
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut T> {
+        self.get_vec_mut(name)?.first_mut()
+    }
+
+    pub fn get_vec(&self, name: &str) -> Option<&Vec<T>> {
+        Some(&self.groups[self.position(name)?].1)
+    }
+
+    /// NOTE: values pushed here are ordered after the inserted ones
+    pub fn get_vec_mut(&mut self, name: &str) -> Option<&mut Vec<T>> {
+        let i = self.position(name)?;
+        Some(&mut self.groups[i].1)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// adds another value under name, after all the others
+    pub fn insert(&mut self, name: String, value: T) {
+        let i = match self.position(&name) {
+            Some(i) => i,
+            None => {
+                self.groups.push((name, Vec::new()));
+                self.groups.len() - 1
+            }
+        };
+        self.groups[i].1.push(value);
+        self.order.push(i);
+    }
+
+    /// removes all values for name
+    pub fn remove(&mut self, name: &str) -> Option<Vec<T>> {
+        let i = self.position(name)?;
+        self.order.retain(|&g| g != i);
+        for g in &mut self.order {
+            if *g > i {
+                *g -= 1;
+            }
+        }
+        Some(self.groups.remove(i).1)
+    }
+
+    /// number of values
+    pub fn len(&self) -> usize {
+        self.groups.iter().map(|(_, values)| values.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.iter().all(|(_, values)| values.is_empty())
+    }
+
+    /// every value, grouped by name
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.groups.iter_mut().flat_map(|(_, values)| values.iter_mut())
+    }
+
+    /// all values for each name, in order of each name's first value
+    pub fn iter_all(&self) -> impl Iterator<Item = (&String, &Vec<T>)> {
+        self.groups.iter().map(|(name, values)| (name, values))
+    }
+
+    /// every value with its name, in insertion order
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter { map: self, used: vec![0; self.groups.len()], order: 0, group: 0 }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a ICalMap<T> {
+    type Item = (&'a str, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over an [ICalMap] in insertion order, see [ICalMap::iter]
+pub struct Iter<'a, T> {
+    map: &'a ICalMap<T>,
+    /// values of each group returned so far
+    used: Vec<usize>,
+    order: usize,
+    /// group whose values pushed through get_vec_mut are next, once order is done
+    group: usize,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (&'a str, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(&g) = self.map.order.get(self.order) {
+            self.order += 1;
+            let (name, values) = &self.map.groups[g];
+            //values may have been removed through get_vec_mut
+            if let Some(value) = values.get(self.used[g]) {
+                self.used[g] += 1;
+                return Some((name.as_str(), value))
+            }
+        }
+        //values pushed through get_vec_mut
+        while let Some((name, values)) = self.map.groups.get(self.group) {
+            if let Some(value) = values.get(self.used[self.group]) {
+                self.used[self.group] += 1;
+                return Some((name.as_str(), value))
+            }
+            self.group += 1;
+        }
+        None
+    }
+}
+
+/// a list of [name, value] pairs in insertion order
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for ICalMap<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for ICalMap<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut map = Self::new();
+        for (name, value) in <Vec<(String, T)> as serde::Deserialize>::deserialize(deserializer)? {
+            map.insert(name, value);
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ICalMap;
+
+    #[test]
+    fn test_map_order() {
+        let mut map = ICalMap::new();
+        map.insert("B".to_string(), 1);
+        map.insert("A".to_string(), 2);
+        map.insert("B".to_string(), 3);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![("B", &1), ("A", &2), ("B", &3)]);
+        assert_eq!(map.get_vec("B").unwrap(), &vec![1, 3]);
+
+        map.get_vec_mut("A").unwrap().push(4);
+        map.get_vec_mut("B").unwrap().remove(0);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![("B", &3), ("A", &2), ("A", &4)]);
+
+        map.remove("B");
+        map.insert("C".to_string(), 5);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![("A", &2), ("C", &5), ("A", &4)]);
+        assert_eq!(map.len(), 3);
+    }
+}
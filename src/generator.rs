@@ -4,8 +4,8 @@ use crate::{
     component::ICalComponent,
     values::{
         ICalValue,
-        binary::*, date::*, datetime::*, duration::*, geo::*, integer::*, period::*, recur::*,
-        text::*,
+        binary::*, cal_address::*, date::*, datetime::*, duration::*, geo::*, integer::*, period::*,
+        recur::*, text::*, unknown::*, uri::*, utc_offset::*,
     },
     property::*
 };
@@ -26,7 +26,7 @@ gen_prop_methods!(
 
     // 3.8.1 Descriptive Component Properties
 
-    attach      Many Either Text Binary,
+    attach      Many Either Uri Binary,
     categories  Many TextList,
     class       One  Text,
     comment     Many Text,
@@ -55,19 +55,19 @@ gen_prop_methods!(
 
     tzid         One Text,
     tzname       One Text,
-    tzoffsetfrom One Text,
-    tzoffsetto   One Text,
-    tzurl        One Text,
+    tzoffsetfrom One UtcOffset,
+    tzoffsetto   One UtcOffset,
+    tzurl        One Uri,
 
 
     // 3.8.4 Relationship Component Properties
 
-    attendee      Many Text,
+    attendee      Many CalAddress,
     contact       Many Text,
-    organizer     One  Text,
+    organizer     One  CalAddress,
     recurrence_id One  Either DateTime Date,
     related_to    Many Text,
-    url           One  Text,
+    url           One  Uri,
     uid           One  Text,
 
     // 3.8.5 Recurrence Component Properties
@@ -90,7 +90,7 @@ gen_prop_methods!(
 
     // 3.8.8 Miscellaneous Component Properties
 
-    request_status Many Text,
+    request_status Many Unknown,
 
     //NOTE: rdate is manually implemented further down
 );
@@ -148,13 +148,13 @@ macro_rules! gen_prop_methods {
             //Returns immutable ref to all values of either types
             $(#[$field_meta])*
             pub fn [<get_ $prop _values>](&self) -> Result<Vec<Either<&[<ICal $typ1>], &[<ICal $typ2>]>>, GetPropError> {
-                Ok(self.props.get_vec(gen_prop_methods!(@prop_name $prop))
+                self.props.get_vec(gen_prop_methods!(@prop_name $prop))
                     .ok_or(GetPropError::PropertyMissing)?
                     .iter()
                     .try_fold(Vec::new(), |mut acc, prop| {
                         acc.push(prop.get_as_either().ok_or(GetPropError::WrongValueType)?);
                         Ok(acc)
-                    })?)
+                    })
             }
         }
     };
@@ -188,13 +188,13 @@ macro_rules! gen_prop_methods {
             //Returns immutable ref to all property values
             $(#[$field_meta])*
             pub fn [<get_ $prop _values>](&self) -> Result<Vec<&[<ICal $typ>]>, GetPropError> {
-                Ok(self.props.get_vec(gen_prop_methods!(@prop_name $prop))
+                self.props.get_vec(gen_prop_methods!(@prop_name $prop))
                     .ok_or(GetPropError::PropertyMissing)?
                     .iter()
                     .try_fold(Vec::new(), |mut acc, prop| {
                         acc.push(prop.get_as().ok_or(GetPropError::WrongValueType)?);
                         Ok(acc)
-                    })?)
+                    })
             }
         }
     };
@@ -235,10 +235,10 @@ macro_rules! gen_prop_methods {
             //Returns immutable ref to either value
             $(#[$field_meta])*
             pub fn [<get_ $prop _value>](&self) -> Result<Either<&[<ICal $typ1>], &[<ICal $typ2>]>, GetPropError> {
-                Ok(self.props.get(gen_prop_methods!(@prop_name $prop))
+                self.props.get(gen_prop_methods!(@prop_name $prop))
                     .ok_or(GetPropError::PropertyMissing)?
                     .get_as_either()
-                    .ok_or(GetPropError::WrongValueType)?)
+                    .ok_or(GetPropError::WrongValueType)
             }
         }
     };
@@ -267,10 +267,10 @@ macro_rules! gen_prop_methods {
             //Returns immutable ref to value
             $(#[$field_meta])*
             pub fn [<get_ $prop _value>](&self) -> Result<&[<ICal $typ>], GetPropError> {
-                Ok(self.props.get(gen_prop_methods!(@prop_name $prop))
+                self.props.get(gen_prop_methods!(@prop_name $prop))
                     .ok_or(GetPropError::PropertyMissing)?
                     .get_as()
-                    .ok_or(GetPropError::WrongValueType)?)
+                    .ok_or(GetPropError::WrongValueType)
             }
         }
     };
@@ -324,12 +324,12 @@ impl ICalComponent {
     }
 
     //Returns immutable ref to value
-    pub fn get_rdate(&self) -> Result<RDateValue, GetPropError> {
+    pub fn get_rdate(&self) -> Result<RDateValue<'_>, GetPropError> {
         let prop = self.props.get("RDATE").ok_or(GetPropError::PropertyMissing)?;
         Ok(match &prop.value {
-            ICalValue::DateList(v) => RDateValue::DateList(&v),
-            ICalValue::DateTimeList(v) => RDateValue::DateTimeList(&v),
-            ICalValue::PeriodList(v) => RDateValue::PeriodList(&v),
+            ICalValue::DateList(v) => RDateValue::DateList(v),
+            ICalValue::DateTimeList(v) => RDateValue::DateTimeList(v),
+            ICalValue::PeriodList(v) => RDateValue::PeriodList(v),
             _ => return Err(GetPropError::WrongValueType)
         })
     }
@@ -0,0 +1,378 @@
+use anyhow::{anyhow, bail, Context};
+use serde_json::{Map, Value};
+
+use crate::{
+    component::{ICalComponent, VCALENDAR, VTIMEZONE},
+    parameter::ICalParameterMap,
+    property::ICalProperty,
+    timezone::{ICalTimeZones, VTimeZone},
+    values::{datetime::ICalDateTime, period::{EndOrDuration, ICalPeriod}, text::{escape, split_escaped, unescape}, ICalValue, ICalValueTrait},
+};
+
+/// RFC 7265 3.3.2: a property whose type isn't known is written as its iCalendar text
+const UNKNOWN: &str = "unknown";
+
+impl ICalComponent {
+    /// RFC 7265: ["vcalendar", [properties], [components]]
+    pub fn to_jcal(&self) -> Value {
+        let name = if self.name.is_empty() { VCALENDAR } else { &self.name };
+        let props = self.props.iter().map(|(name, prop)| prop.to_jcal(name)).collect();
+        let comps = self.comps.iter().map(|(_, comp)| comp.to_jcal()).collect();
+        Value::Array(vec![name.to_lowercase().into(), Value::Array(props), Value::Array(comps)])
+    }
+
+    /// Parse any jCal component, TZIDs are resolved against its VTIMEZONEs, then the IANA database
+    pub fn from_jcal(jcal: &Value) -> anyhow::Result<Self> {
+        let mut tzs = ICalTimeZones::default();
+        for comp in jcal_component(jcal)?.2 {
+            if jcal_component(comp)?.0.eq_ignore_ascii_case(VTIMEZONE) {
+                let vtimezone = component_from_jcal(comp, &tzs)?;
+                tzs.insert(VTimeZone::from_component(&vtimezone)?);
+            }
+        }
+        component_from_jcal(jcal, &tzs)
+    }
+}
+
+impl ICalProperty {
+    /// RFC 7265 3.4: [name, {parameters}, type, value, ...]
+    pub fn to_jcal(&self, name: &str) -> Value {
+        //RFC 7265 3.3.1.3: structured text (ex. REQUEST-STATUS) is an array of its parts
+        let structured = match (&self.value, default_type(name)) {
+            (ICalValue::Unknown(raw), Some("text")) => Some(structured_to_jcal(raw)),
+            _ => None,
+        };
+        let kind = match structured {
+            Some(_) => "text",
+            None => value_type(&self.value),
+        };
+
+        //TZID always follows the time zone of DATE-TIME values, VALUE is the type
+        let mut params = Map::new();
+        let has_date_times = !self.value.date_times().is_empty();
+        for param in self.params.iter().filter(|param| !(param.name == "VALUE" || has_date_times && param.name == "TZID")) {
+            let value = match param.values.as_slice() {
+                [value] => value.clone().into(),
+                values => values.to_vec().into(),
+            };
+            params.insert(param.name.to_lowercase(), value);
+        }
+        if let Some(tzid) = self.value.tzid() {
+            params.insert("tzid".to_string(), tzid.into());
+        }
+
+        let mut prop = vec![name.to_lowercase().into(), Value::Object(params), kind.into()];
+        match structured {
+            Some(parts) => prop.push(parts),
+            None => prop.extend(values_to_jcal(&self.value)),
+        }
+        Value::Array(prop)
+    }
+}
+
+/// (name, properties, components)
+fn jcal_component(jcal: &Value) -> anyhow::Result<(&str, &Vec<Value>, &Vec<Value>)> {
+    match jcal.as_array().map(Vec::as_slice) {
+        Some([Value::String(name), Value::Array(props), Value::Array(comps)]) => Ok((name, props, comps)),
+        _ => bail!("jCal component must be [name, [properties], [components]]"),
+    }
+}
+
+fn component_from_jcal(jcal: &Value, tzs: &ICalTimeZones) -> anyhow::Result<ICalComponent> {
+    let (name, props, comps) = jcal_component(jcal)?;
+    let mut comp = ICalComponent::named(&name.to_uppercase());
+    for prop in props {
+        let (prop_name, prop) = property_from_jcal(prop, tzs)
+            .with_context(|| format!("Parsing jCal property {prop} in {}", comp.name))?;
+        comp.props.insert(prop_name, prop);
+    }
+    for child in comps {
+        let child = component_from_jcal(child, tzs)?;
+        comp.comps.insert(child.name.clone(), child);
+    }
+    Ok(comp)
+}
+
+fn property_from_jcal(jcal: &Value, tzs: &ICalTimeZones) -> anyhow::Result<(String, ICalProperty)> {
+    let Some([Value::String(name), Value::Object(jcal_params), Value::String(kind), values @ ..]) = jcal.as_array().map(Vec::as_slice) else {
+        bail!("jCal property must be [name, {{parameters}}, type, value, ...]");
+    };
+    if values.is_empty() {
+        bail!("jCal property has no value");
+    }
+    let name = name.to_uppercase();
+    let kind = kind.to_lowercase();
+
+    let mut params = ICalParameterMap::new();
+    for (param, value) in jcal_params {
+        let values = match value {
+            Value::Array(values) => values.iter().map(param_value).collect(),
+            value => vec![param_value(value)],
+        };
+        params.insert_values(&param.to_uppercase(), values);
+    }
+
+    let text = values.iter()
+        .map(|value| value_to_ical(&kind, value))
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .join(",");
+    let value = match kind == UNKNOWN || default_type(&name) == Some(kind.as_str()) {
+        true => ICalValue::from_default(&name, &text, &params, tzs)?,
+        false => {
+            let value_param = kind.to_uppercase();
+            params.insert("VALUE", &value_param);
+            ICalValue::from_value_param(&value_param, &text, &params, tzs)?
+        },
+    };
+    Ok((name, ICalProperty::new(value, params)))
+}
+
+fn param_value(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// RFC 7265 3.3: the type of each property known to RFC 5545
+fn default_type(name: &str) -> Option<&'static str> {
+    Some(match name.to_uppercase().as_str() {
+        "ATTACH" | "TZURL" | "URL" => "uri",
+        "ATTENDEE" | "ORGANIZER" => "cal-address",
+        "COMPLETED" | "CREATED" | "DTEND" | "DTSTAMP" | "DTSTART" | "DUE" |
+        "EXDATE" | "LAST-MODIFIED" | "RDATE" | "RECURRENCE-ID" => "date-time",
+        "DURATION" | "TRIGGER" => "duration",
+        "FREEBUSY" => "period",
+        "GEO" => "float",
+        "PERCENT-COMPLETE" | "PRIORITY" | "REPEAT" | "SEQUENCE" => "integer",
+        "RRULE" => "recur",
+        "TZOFFSETFROM" | "TZOFFSETTO" => "utc-offset",
+        "ACTION" | "CALSCALE" | "CATEGORIES" | "CLASS" | "COMMENT" | "CONTACT" | "DESCRIPTION" |
+        "LOCATION" | "METHOD" | "PRODID" | "RELATED-TO" | "REQUEST-STATUS" | "RESOURCES" |
+        "STATUS" | "SUMMARY" | "TRANSP" | "TZID" | "TZNAME" | "UID" | "VERSION" => "text",
+        _ => return None,
+    })
+}
+
+/// RFC 7265 3.5
+fn value_type(value: &ICalValue) -> &'static str {
+    match value {
+        ICalValue::Binary(_) => "binary",
+        ICalValue::Boolean(_) => "boolean",
+        ICalValue::Date(_) | ICalValue::DateList(_) => "date",
+        ICalValue::DateTime(_) | ICalValue::DateTimeList(_) => "date-time",
+        ICalValue::Time(_) => "time",
+        ICalValue::Duration(_) => "duration",
+        ICalValue::Float(_) | ICalValue::Geo(_) => "float",
+        ICalValue::Integer(_) => "integer",
+        ICalValue::Period(_) | ICalValue::PeriodList(_) => "period",
+        ICalValue::Recur(_) => "recur",
+        ICalValue::Text(_) | ICalValue::TextList(_) => "text",
+        ICalValue::UtcOffset(_) => "utc-offset",
+        ICalValue::CalAddress(_) => "cal-address",
+        ICalValue::Uri(_) => "uri",
+        ICalValue::Unknown(_) => UNKNOWN,
+    }
+}
+
+/// lists are written as one jCal value each
+fn values_to_jcal(value: &ICalValue) -> Vec<Value> {
+    let one = |value: Value| vec![value];
+    match value {
+        ICalValue::Boolean(b) => one((*b).into()),
+        ICalValue::Date(date) => one(date.format("%Y-%m-%d").to_string().into()),
+        ICalValue::DateList(dates) => dates.iter().map(|date| date.format("%Y-%m-%d").to_string().into()).collect(),
+        ICalValue::DateTime(dt) => one(date_time_to_jcal(dt).into()),
+        ICalValue::DateTimeList(dts) => dts.iter().map(|dt| date_time_to_jcal(dt).into()).collect(),
+        ICalValue::Time(time) => one(with_colons(&time.serialize(), 2).into()),
+        ICalValue::Float(f) => one((*f).into()),
+        ICalValue::Geo((lat, lon)) => one(vec![*lat, *lon].into()),
+        ICalValue::Integer(i) => one((*i).into()),
+        ICalValue::Period(period) => one(period_to_jcal(period)),
+        ICalValue::PeriodList(periods) => periods.iter().map(period_to_jcal).collect(),
+        ICalValue::Recur(recur) => one(recur_to_jcal(&recur.serialize())),
+        ICalValue::Text(text) => one(text.clone().into()),
+        ICalValue::TextList(texts) => texts.iter().map(|text| text.clone().into()).collect(),
+        ICalValue::UtcOffset(offset) => one(with_colons(&offset.serialize(), 3).into()),
+        ICalValue::Binary(_) | ICalValue::Duration(_) | ICalValue::CalAddress(_) | ICalValue::Uri(_) | ICalValue::Unknown(_)
+            => one(value.serialize().into()),
+    }
+}
+
+/// ["2.0", "Success"], each part unescaped
+fn structured_to_jcal(raw: &str) -> Value {
+    split_escaped(raw, ';').into_iter().map(|part| Value::String(unescape(part))).collect()
+}
+
+/// RFC 7265 3.3.5: "2024-07-01T09:00:00", with a "Z" for UTC
+fn date_time_to_jcal(dt: &ICalDateTime) -> String {
+    let suffix = match dt {
+        ICalDateTime::Zoned(zoned) if zoned.timezone().is_utc() => "Z",
+        _ => "",
+    };
+    dt.naive_local().format("%Y-%m-%dT%H:%M:%S").to_string() + suffix
+}
+
+/// RFC 7265 3.6.9: [start, end or duration]
+fn period_to_jcal(period: &ICalPeriod) -> Value {
+    let end = match &period.end_or_duration {
+        EndOrDuration::End(end) => date_time_to_jcal(end),
+        EndOrDuration::Duration(duration) => duration.serialize(),
+    };
+    vec![date_time_to_jcal(&period.start), end].into()
+}
+
+/// RFC 7265 3.6.10: rule parts as lowercase keys, numbers as numbers and several values as arrays
+fn recur_to_jcal(recur: &str) -> Value {
+    let mut object = Map::new();
+    for part in recur.split(';') {
+        let Some((name, value)) = part.split_once('=') else { continue };
+        let value = match name {
+            "FREQ" | "WKST" => value.into(),
+            "UNTIL" => match value.len() {
+                8 => with_dashes(value),
+                _ => with_dashes(&value[..8]) + "T" + &with_colons(&value[9..], 2),
+            }.into(),
+            _ => {
+                let mut values: Vec<Value> = value.split(',')
+                    .map(|value| value.parse::<i64>().map_or_else(|_| value.into(), Value::from))
+                    .collect();
+                match values.len() {
+                    1 => values.remove(0),
+                    _ => values.into(),
+                }
+            },
+        };
+        object.insert(name.to_lowercase(), value);
+    }
+    Value::Object(object)
+}
+
+/// "20240701" to "2024-07-01"
+fn with_dashes(date: &str) -> String {
+    format!("{}-{}-{}", &date[..4], &date[4..6], &date[6..])
+}
+
+/// "090000Z" to "09:00:00Z" or "+0530" to "+05:30" (skipping a sign)
+fn with_colons(value: &str, first: usize) -> String {
+    let mut result = value[..first].to_string();
+    for (i, c) in value[first..].char_indices() {
+        if i % 2 == 0 && c.is_ascii_digit() {
+            result.push(':');
+        }
+        result.push(c);
+    }
+    result
+}
+
+/// the iCalendar text of a jCal value, ready for [ICalValueTrait::parse]
+fn value_to_ical(kind: &str, value: &Value) -> anyhow::Result<String> {
+    Ok(match (kind, value) {
+        ("period", Value::Array(parts)) => {
+            let parts = parts.iter()
+                .map(|part| part.as_str().ok_or(anyhow!("jCal period must be strings")))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let [start, end] = parts.as_slice() else { bail!("jCal period must be [start, end or duration]") };
+            let end = match end.trim_start_matches(['+', '-']).starts_with('P') {
+                true => end.to_string(),
+                false => end.replace(['-', ':'], ""),
+            };
+            format!("{}/{end}", start.replace(['-', ':'], ""))
+        },
+        ("recur", Value::Object(parts)) => recur_to_ical(parts)?,
+        //GEO
+        ("float", Value::Array(values)) => values.iter().map(Value::to_string).collect::<Vec<_>>().join(","),
+        //structured text (ex. REQUEST-STATUS)
+        ("text", Value::Array(values)) => values.iter().map(|value| escape(&param_value(value))).collect::<Vec<_>>().join(";"),
+        ("text", Value::String(text)) => escape(text),
+        ("date" | "date-time", Value::String(dt)) => dt.replace(['-', ':'], ""),
+        ("time" | "utc-offset", Value::String(value)) => value.replace(':', ""),
+        ("boolean", Value::Bool(b)) => b.to_string().to_uppercase(),
+        (_, Value::String(value)) => value.clone(),
+        (_, Value::Number(n)) => n.to_string(),
+        (kind, value) => bail!("Invalid jCal {kind} value {value}"),
+    })
+}
+
+/// FREQ comes first, as it does in RFC 5545
+fn recur_to_ical(parts: &Map<String, Value>) -> anyhow::Result<String> {
+    let freq = parts.get("freq").and_then(Value::as_str).ok_or(anyhow!("jCal recur missing freq"))?;
+    let mut rules = vec![format!("FREQ={}", freq.to_uppercase())];
+    for (name, value) in parts.iter().filter(|(name, _)| *name != "freq") {
+        let values = match value {
+            Value::Array(values) => values.iter().map(param_value).collect(),
+            value => vec![param_value(value)],
+        };
+        let mut value = values.join(",");
+        if name == "until" {
+            value = value.replace(['-', ':'], "");
+        }
+        rules.push(format!("{}={value}", name.to_uppercase()));
+    }
+    Ok(rules.join(";"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::component::ICalComponent;
+
+    #[test]
+    fn test_jcal() {
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTAMP:20240101T120000Z\r\nDTSTART;TZID=Europe/Berlin:20240701T090000\r\nDTEND;VALUE=DATE:20240702\r\nRRULE:FREQ=WEEKLY;UNTIL=20241231T230000Z;BYDAY=MO,-1FR;BYMONTH=7\r\nRDATE;VALUE=PERIOD:20240801T090000/PT1H,20240802T090000/20240802T100000\r\nEXDATE;TZID=Custom:20240708T090000,20240715T090000\r\nCATEGORIES:A\\,B,C\r\nATTENDEE;MEMBER=\"mailto:a@example.com\",\"mailto:b@example.com\":mailto:jane@example.com\r\nSUMMARY:Lunch\\; then review\r\nPRIORITY:1\r\nREQUEST-STATUS:3.1;Invalid property value;DTSTART:20240701T09\\;00\r\nX-NUM;VALUE=INTEGER:7\r\nX-OTHER:a\\,b\r\nEND:VEVENT\r\nBEGIN:VTIMEZONE\r\nTZID:Custom\r\nBEGIN:STANDARD\r\nDTSTART:19700101T000000\r\nTZOFFSETFROM:+0530\r\nTZOFFSETTO:+0530\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\nEND:VCALENDAR\r\n";
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        let jcal = vcal.to_jcal();
+
+        let vevent = &jcal[2][0];
+        assert_eq!(jcal[0], "vcalendar");
+        assert_eq!(vevent[1][1], json!(["dtstamp", {}, "date-time", "2024-01-01T12:00:00Z"]));
+        assert_eq!(vevent[1][2], json!(["dtstart", {"tzid": "Europe/Berlin"}, "date-time", "2024-07-01T09:00:00"]));
+        assert_eq!(vevent[1][3], json!(["dtend", {}, "date", "2024-07-02"]));
+        assert_eq!(vevent[1][4], json!(["rrule", {}, "recur", {"freq": "WEEKLY", "until": "2024-12-31T23:00:00Z", "byday": ["MO", "-1FR"], "bymonth": 7}]));
+        assert_eq!(vevent[1][5], json!(["rdate", {}, "period", ["2024-08-01T09:00:00", "PT1H"], ["2024-08-02T09:00:00", "2024-08-02T10:00:00"]]));
+        assert_eq!(vevent[1][6], json!(["exdate", {"tzid": "Custom"}, "date-time", "2024-07-08T09:00:00", "2024-07-15T09:00:00"]));
+        assert_eq!(vevent[1][7], json!(["categories", {}, "text", "A,B", "C"]));
+        assert_eq!(vevent[1][8][1], json!({"member": ["mailto:a@example.com", "mailto:b@example.com"]}));
+        assert_eq!(vevent[1][10], json!(["priority", {}, "integer", 1]));
+        assert_eq!(vevent[1][11], json!(["request-status", {}, "text", ["3.1", "Invalid property value", "DTSTART:20240701T09;00"]]));
+        assert_eq!(vevent[1][12], json!(["x-num", {}, "integer", 7]));
+        assert_eq!(vevent[1][13], json!(["x-other", {}, "unknown", "a\\,b"]));
+        assert_eq!(jcal[2][1][2][0][1][1], json!(["tzoffsetfrom", {}, "utc-offset", "+05:30"]));
+
+        let out = ICalComponent::from_jcal(&jcal).unwrap();
+        assert_eq!(out.to_ics(), vcal.to_ics());
+        assert_eq!(out.to_jcal(), jcal);
+    }
+
+    #[test]
+    fn test_from_jcal() {
+        //RFC 7265 Appendix B.1
+        let jcal = json!(["vcalendar",
+            [
+                ["calscale", {}, "text", "GREGORIAN"],
+                ["prodid", {}, "text", "-//Example Inc.//Example Calendar//EN"],
+                ["version", {}, "text", "2.0"]
+            ],
+            [
+                ["vevent",
+                    [
+                        ["dtstamp", {}, "date-time", "2008-02-05T19:12:24Z"],
+                        ["dtstart", {}, "date", "2008-10-06"],
+                        ["summary", {}, "text", "Planning meeting"],
+                        ["uid", {}, "text", "4088E990AD89CB3DBB484909"]
+                    ],
+                    []
+                ]
+            ]
+        ]);
+        let mut vcal = ICalComponent::from_jcal(&jcal).unwrap();
+        assert!(vcal.to_ics().contains("\r\nDTSTART;VALUE=DATE:20081006\r\n"));
+        assert_eq!(vcal.expect_vevent().get_summary_value().unwrap(), "Planning meeting");
+
+        let Err(err) = ICalComponent::from_jcal(&json!(["vcalendar", [["dtstart", {}, "date-time", "bad"]], []])) else {
+            panic!("bad DTSTART should fail")
+        };
+        assert!(err.to_string().starts_with("Parsing jCal property"), "{err}");
+    }
+}
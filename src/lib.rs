@@ -1,9 +1,14 @@
-#[macro_use]
-
 pub mod parser;
 pub mod serializer;
 pub mod values;
 pub mod component;
 pub mod property;
+pub mod parameter;
 pub mod generator;
+pub mod recurrence;
+pub mod query;
+pub mod timezone;
+pub mod jcal;
+pub mod xcal;
+pub mod caldav;
 mod test;
@@ -0,0 +1,425 @@
+use std::fmt::Display;
+
+use crate::values::{cal_address::ICalCalAddress, uri::ICalUri, ICalValueTrait};
+
+/// RFC 5545 3.2: param = param-name "=" param-value *("," param-value)
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ICalParameter {
+    pub name: String,
+    pub values: Vec<String>,
+}
+
+/// The parameters of a property, in their original order
+/// NOTE: names are case-insensitive and stored uppercase
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ICalParameterMap(Vec<ICalParameter>);
+
+impl ICalParameterMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns the first value of the parameter
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.get_values(name)?.first().map(String::as_str)
+    }
+
+    /// returns every value of a multi-valued parameter (ex. MEMBER)
+    pub fn get_values(&self, name: &str) -> Option<&[String]> {
+        self.find(name).map(|param| param.values.as_slice())
+    }
+
+    /// replaces or creates the parameter with a single value
+    pub fn insert(&mut self, name: &str, value: &str) -> &mut Self {
+        self.insert_values(name, vec![value.to_string()])
+    }
+
+    /// replaces or creates the parameter, keeping its position if it exists
+    pub fn insert_values(&mut self, name: &str, values: Vec<String>) -> &mut Self {
+        match self.0.iter_mut().find(|param| param.name.eq_ignore_ascii_case(name)) {
+            Some(param) => param.values = values,
+            None => self.0.push(ICalParameter { name: name.to_uppercase(), values }),
+        }
+        self
+    }
+
+    /// adds another value to the parameter (or creates it)
+    pub fn append(&mut self, name: &str, value: &str) -> &mut Self {
+        match self.0.iter_mut().find(|param| param.name.eq_ignore_ascii_case(name)) {
+            Some(param) => param.values.push(value.to_string()),
+            None => return self.insert(name, value),
+        }
+        self
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<ICalParameter> {
+        let index = self.0.iter().position(|param| param.name.eq_ignore_ascii_case(name))?;
+        Some(self.0.remove(index))
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.find(name).is_some()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, ICalParameter> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn find(&self, name: &str) -> Option<&ICalParameter> {
+        self.0.iter().find(|param| param.name.eq_ignore_ascii_case(name))
+    }
+
+    fn get_typed<T: ICalParamValue>(&self, name: &str) -> Option<T> {
+        self.get(name).and_then(T::parse_param)
+    }
+
+    fn get_typed_values<T: ICalParamValue>(&self, name: &str) -> Vec<T> {
+        self.get_values(name).into_iter().flatten()
+            .filter_map(|value| T::parse_param(value))
+            .collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a ICalParameterMap {
+    type Item = &'a ICalParameter;
+    type IntoIter = std::slice::Iter<'a, ICalParameter>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> FromIterator<(&'a str, &'a str)> for ICalParameterMap {
+    fn from_iter<I: IntoIterator<Item = (&'a str, &'a str)>>(iter: I) -> Self {
+        let mut params = Self::new();
+        for (name, value) in iter {
+            params.append(name, value);
+        }
+        params
+    }
+}
+
+/// A typed parameter value
+pub trait ICalParamValue: Sized {
+    /// None if the value is invalid for this type
+    fn parse_param(value: &str) -> Option<Self>;
+    fn serialize_param(&self) -> String;
+}
+
+impl ICalParamValue for String {
+    fn parse_param(value: &str) -> Option<Self> {
+        Some(value.to_string())
+    }
+
+    fn serialize_param(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ICalParamValue for bool {
+    fn parse_param(value: &str) -> Option<Self> {
+        match value.to_uppercase().as_str() {
+            "TRUE" => Some(true),
+            "FALSE" => Some(false),
+            _ => None,
+        }
+    }
+
+    fn serialize_param(&self) -> String {
+        (if *self { "TRUE" } else { "FALSE" }).to_string()
+    }
+}
+
+impl ICalParamValue for ICalUri {
+    fn parse_param(value: &str) -> Option<Self> {
+        ICalUri::parse(value).ok()
+    }
+
+    fn serialize_param(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ICalParamValue for ICalCalAddress {
+    fn parse_param(value: &str) -> Option<Self> {
+        ICalCalAddress::parse(value, &ICalParameterMap::new()).ok()
+    }
+
+    fn serialize_param(&self) -> String {
+        self.serialize()
+    }
+}
+
+gen_param_enum!(
+    /// RFC 5545 3.2.3 CUTYPE
+    #[derive(Default)]
+    CalendarUserType {
+        #[default]
+        Individual = "INDIVIDUAL",
+        Group = "GROUP",
+        Resource = "RESOURCE",
+        Room = "ROOM",
+        Unknown = "UNKNOWN",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.7 ENCODING
+    #[derive(Default)]
+    Encoding {
+        #[default]
+        EightBit = "8BIT",
+        Base64 = "BASE64",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.9 FBTYPE
+    #[derive(Default)]
+    FreeBusyType {
+        Free = "FREE",
+        #[default]
+        Busy = "BUSY",
+        BusyUnavailable = "BUSY-UNAVAILABLE",
+        BusyTentative = "BUSY-TENTATIVE",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.12 PARTSTAT
+    #[derive(Default)]
+    ParticipationStatus {
+        #[default]
+        NeedsAction = "NEEDS-ACTION",
+        Accepted = "ACCEPTED",
+        Declined = "DECLINED",
+        Tentative = "TENTATIVE",
+        Delegated = "DELEGATED",
+        Completed = "COMPLETED",
+        InProcess = "IN-PROCESS",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.13 RANGE
+    Range {
+        ThisAndFuture = "THISANDFUTURE",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.14 RELATED
+    #[derive(Default)]
+    AlarmTriggerRelationship {
+        #[default]
+        Start = "START",
+        End = "END",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.15 RELTYPE
+    #[derive(Default)]
+    RelationshipType {
+        #[default]
+        Parent = "PARENT",
+        Child = "CHILD",
+        Sibling = "SIBLING",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.16 ROLE
+    #[derive(Default)]
+    ParticipationRole {
+        Chair = "CHAIR",
+        #[default]
+        ReqParticipant = "REQ-PARTICIPANT",
+        OptParticipant = "OPT-PARTICIPANT",
+        NonParticipant = "NON-PARTICIPANT",
+    }
+);
+
+gen_param_enum!(
+    /// RFC 5545 3.2.20 VALUE
+    ValueType {
+        Binary = "BINARY",
+        Boolean = "BOOLEAN",
+        CalAddress = "CAL-ADDRESS",
+        Date = "DATE",
+        DateTime = "DATE-TIME",
+        Duration = "DURATION",
+        Float = "FLOAT",
+        Integer = "INTEGER",
+        Period = "PERIOD",
+        Recur = "RECUR",
+        Text = "TEXT",
+        Time = "TIME",
+        Uri = "URI",
+        UtcOffset = "UTC-OFFSET",
+    }
+);
+
+gen_param_methods!(
+    altrep         "ALTREP"         One  ICalUri,
+    cn             "CN"             One  String,
+    cutype         "CUTYPE"         One  CalendarUserType,
+    delegated_from "DELEGATED-FROM" Many ICalCalAddress,
+    delegated_to   "DELEGATED-TO"   Many ICalCalAddress,
+    dir            "DIR"            One  ICalUri,
+    encoding       "ENCODING"       One  Encoding,
+    fmttype        "FMTTYPE"        One  String,
+    fbtype         "FBTYPE"         One  FreeBusyType,
+    language       "LANGUAGE"       One  String,
+    member         "MEMBER"         Many ICalCalAddress,
+    partstat       "PARTSTAT"       One  ParticipationStatus,
+    range          "RANGE"          One  Range,
+    related        "RELATED"        One  AlarmTriggerRelationship,
+    reltype        "RELTYPE"        One  RelationshipType,
+    role           "ROLE"           One  ParticipationRole,
+    rsvp           "RSVP"           One  bool,
+    sent_by        "SENT-BY"        One  ICalCalAddress,
+    tzid           "TZID"           One  String,
+    value_type     "VALUE"          One  ValueType,
+);
+
+/// Generates an enum for a parameter's values, with Other for x-name & iana-token values
+macro_rules! gen_param_enum {
+    (
+        $(#[$enum_meta:meta])*
+        $name:ident {
+            $(
+                $(#[$variant_meta:meta])*
+                $variant:ident = $value:literal,
+            )+
+        }
+    ) => {
+        $(#[$enum_meta])*
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub enum $name {
+            $(
+                $(#[$variant_meta])*
+                $variant,
+            )+
+            /// x-name or iana-token
+            Other(String),
+        }
+
+        impl ICalParamValue for $name {
+            /// case-insensitive, unknown values are kept as Other
+            fn parse_param(value: &str) -> Option<Self> {
+                Some(match value.to_uppercase().as_str() {
+                    $(
+                        $value => Self::$variant,
+                    )+
+                    _ => Self::Other(value.to_string()),
+                })
+            }
+
+            fn serialize_param(&self) -> String {
+                self.to_string()
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                match self {
+                    $(
+                        Self::$variant => write!(f, $value),
+                    )+
+                    Self::Other(value) => write!(f, "{value}"),
+                }
+            }
+        }
+    };
+}
+
+pub(crate) use gen_param_enum;
+
+/// Generates typed getters & setters for RFC 5545 3.2 parameters
+macro_rules! gen_param_methods {
+    ($($method:ident $name:literal $count:ident $typ:ty,)+) => {
+        impl ICalParameterMap {
+            $(
+                gen_param_methods!(@methods $method $name $count $typ);
+            )+
+        }
+    };
+
+    (@methods $method:ident $name:literal One $typ:ty) => {
+        paste::paste! {
+            #[doc = concat!("the typed value of ", $name, ", None if missing or invalid")]
+            pub fn $method(&self) -> Option<$typ> {
+                self.get_typed($name)
+            }
+
+            #[doc = concat!("replaces or creates ", $name)]
+            pub fn [<set_ $method>](&mut self, value: $typ) -> &mut Self {
+                self.insert($name, &value.serialize_param())
+            }
+        }
+    };
+
+    (@methods $method:ident $name:literal Many $typ:ty) => {
+        paste::paste! {
+            #[doc = concat!("the typed values of ", $name, ", skipping invalid ones")]
+            pub fn $method(&self) -> Vec<$typ> {
+                self.get_typed_values($name)
+            }
+
+            #[doc = concat!("replaces or creates ", $name)]
+            pub fn [<set_ $method>](&mut self, values: Vec<$typ>) -> &mut Self {
+                self.insert_values($name, values.iter().map(ICalParamValue::serialize_param).collect())
+            }
+        }
+    };
+}
+
+pub(crate) use gen_param_methods;
+
+#[cfg(test)]
+mod tests {
+    use crate::parameter::*;
+
+    #[test]
+    fn test_param_order_and_case() {
+        let mut params: ICalParameterMap = [("cn", "Jane"), ("ROLE", "CHAIR"), ("x-custom", "1")].into_iter().collect();
+        params.insert("Role", "OPT-PARTICIPANT");
+        let names: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["CN", "ROLE", "X-CUSTOM"]);
+        assert_eq!(params.get("x-CUSTOM"), Some("1"));
+        assert_eq!(params.role(), Some(ParticipationRole::OptParticipant));
+    }
+
+    #[test]
+    fn test_typed_params() {
+        let mut params = ICalParameterMap::new();
+        params.set_partstat(ParticipationStatus::Accepted)
+            .set_rsvp(true)
+            .set_member(vec![
+                ICalCalAddress::mailto("a@example.com").unwrap(),
+                ICalCalAddress::mailto("b@example.com").unwrap(),
+            ]);
+        assert_eq!(params.get("PARTSTAT"), Some("ACCEPTED"));
+        assert_eq!(params.get("RSVP"), Some("TRUE"));
+        assert_eq!(params.get_values("MEMBER").unwrap(), ["mailto:a@example.com", "mailto:b@example.com"]);
+        assert_eq!(params.member()[1].email(), Some("b@example.com"));
+        assert_eq!(params.cutype(), None);
+        assert_eq!(params.cutype().unwrap_or_default(), CalendarUserType::Individual);
+
+        params.insert("CUTYPE", "x-bot");
+        assert_eq!(params.cutype(), Some(CalendarUserType::Other("x-bot".to_string())));
+        params.insert("FBTYPE", "busy-tentative");
+        assert_eq!(params.fbtype(), Some(FreeBusyType::BusyTentative));
+    }
+}
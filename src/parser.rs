@@ -1,86 +1,426 @@
-use std::{collections::HashMap, iter::Peekable, str::Lines};
-use anyhow::{anyhow, bail};
+use std::{borrow::Cow, collections::HashMap};
 
-use crate::{component::{ICalComponent, ICalComponentMap, ICalPropertyMap}, property::ICalProperty};
+use crate::{component::{ICalComponent, VCALENDAR, VTIMEZONE}, property::{ICalParameterMap, ICalProperty, ICalRawLine}, serializer::CRLF, timezone::{ICalTimeZones, VTimeZone}, values::{unknown::ICalUnknown, ICalValue}};
+
+pub mod error;
+pub mod stream;
+mod bytes;
+
+use error::ParseError;
+
+/// Options for [ICalComponent::from_ics_with]
+#[derive(Clone, Debug, Default)]
+pub struct ICalParseOptions {
+    /// keep going on errors, collecting them as warnings
+    /// NOTE: unparseable values become ICalValue::Unknown and malformed lines are kept in raw_lines
+    /// (not ICalValue::Text, which would escape the value again when written back)
+    pub lenient: bool,
+    /// keep each property's original text, written back as is while it's unmodified
+    pub keep_raw: bool,
+}
+
+/// The result of [ICalComponent::from_ics_with] or [ICalComponent::from_ics_component_with]
+pub struct ICalParsed {
+    /// ex. "VCALENDAR" or "VEVENT", as in [ICalComponent::name]
+    pub name: String,
+    pub component: ICalComponent,
+    /// errors skipped in lenient mode
+    pub warnings: Vec<ParseError>,
+}
+
+/// The result of [ICalComponent::from_ics_all_with]
+pub struct ICalParsedAll {
+    /// top-level components with their names, in order
+    pub components: Vec<(String, ICalComponent)>,
+    /// errors skipped in lenient mode
+    pub warnings: Vec<ParseError>,
+}
 
 impl ICalComponent {
     //Parse a VCalendar component from an ICalendar string
-    //NOTE: ICal string MUST begin and end with VCALENDAR
-    pub fn from_ics(ics: &str) -> anyhow::Result<Self> {
-        let mut lines = ics.lines().peekable();
-        let begin_line = lines.next().ok_or(anyhow!("ICal string is empty!"))?.to_uppercase();
-        if begin_line != "BEGIN:VCALENDAR" {
-            bail!("ICal started with {begin_line} not BEGIN:VCALENDAR!")
+    //NOTE: ICal string MUST contain exactly one VCALENDAR, content after it is an error
+    //TZIDs are resolved against the calendar's VTIMEZONEs, then the IANA database
+    pub fn from_ics(ics: &str) -> Result<Self, ParseError> {
+        Ok(Self::from_ics_with(ics, &ICalParseOptions::default())?.component)
+    }
+
+    pub fn from_ics_with(ics: &str, options: &ICalParseOptions) -> Result<ICalParsed, ParseError> {
+        Self::parse_single(str_lines(ics), Some(VCALENDAR), options)
+    }
+
+    /// Parse a standalone component of any name (ex. a VEVENT, VTODO or VCARD block)
+    pub fn from_ics_component(ics: &str) -> Result<(String, Self), ParseError> {
+        let parsed = Self::from_ics_component_with(ics, &ICalParseOptions::default())?;
+        Ok((parsed.name, parsed.component))
+    }
+
+    pub fn from_ics_component_with(ics: &str, options: &ICalParseOptions) -> Result<ICalParsed, ParseError> {
+        Self::parse_single(str_lines(ics), None, options)
+    }
+
+    /// Parse every top-level component in a stream (ex. concatenated VCALENDARs from an export)
+    /// NOTE: VTIMEZONEs are shared between all the components
+    pub fn from_ics_all(ics: &str) -> Result<Vec<(String, Self)>, ParseError> {
+        Ok(Self::from_ics_all_with(ics, &ICalParseOptions::default())?.components)
+    }
+
+    pub fn from_ics_all_with(ics: &str, options: &ICalParseOptions) -> Result<ICalParsedAll, ParseError> {
+        let mut parser = Parser::new(str_lines(ics), Self::collect_timezones(str_lines(ics)), options);
+        let mut components = Vec::new();
+        while let Some(root) = parser.parse_root(None)? {
+            components.push((root.name.clone(), root));
+        }
+        Ok(ICalParsedAll { components, warnings: parser.warnings })
+    }
+
+    fn parse_single<'a, L>(lines: L, expected: Option<&str>, options: &ICalParseOptions) -> Result<ICalParsed, ParseError>
+    where
+        L: Iterator<Item = (usize, Cow<'a, str>)> + Clone,
+    {
+        let mut parser = Parser::new(lines.clone(), Self::collect_timezones(lines), options);
+        let component = parser.parse_root(expected)?
+            .ok_or(ParseError::new("ICal string is empty!", "").at_line(1, &[]))?;
+        parser.parse_trailing(&component.name)?;
+        Ok(ICalParsed { name: component.name.clone(), component, warnings: parser.warnings })
+    }
+
+    /// first pass: parse only the VTIMEZONE components (they may come after their uses)
+    /// NOTE: lines are only copied when folded
+    fn collect_timezones<'a>(lines: impl Iterator<Item = (usize, Cow<'a, str>)>) -> ICalTimeZones {
+        let mut tzs = ICalTimeZones::default();
+        let mut parser = Parser::new(lines, ICalTimeZones::default(), &ICalParseOptions::default());
+        while let Some((number, line)) = parser.next_unfolded() {
+            if !line.eq_ignore_ascii_case("BEGIN:VTIMEZONE") {
+                continue
+            }
+            let comp = parser.parse_component(VTIMEZONE, number);
+            if let Some(vtz) = comp.ok().and_then(|comp| VTimeZone::from_component(&comp).ok()) {
+                tzs.insert(vtz);
+            }
         }
-        Ok(Self::_from_ics("VCALENDAR".into(), &mut lines)?)
+        tzs
     }
+}
+
+/// physical lines with their 0-based index, without any BOM
+fn str_lines(ics: &str) -> impl Iterator<Item = (usize, Cow<'_, str>)> + Clone {
+    let ics = ics.strip_prefix('\u{FEFF}').unwrap_or(ics);
+    ics.lines().map(Cow::Borrowed).enumerate()
+}
 
-    fn _from_ics(component_name: &str, lines: &mut Peekable<Lines>) -> anyhow::Result<ICalComponent> {
-        let mut props = ICalPropertyMap::new();
-        let mut comps = ICalComponentMap::new();
+/// what a content line did to the component being parsed
+enum Step {
+    Continue,
+    Child(ICalComponent),
+    End,
+}
 
-        while let Some(line) = lines.next() {
-            let line = unfold_line(line, lines);
-            let cl = ContentLine::parse(&line)?;
-            let name = cl.name.clone();
+/// a component that has begun but not ended
+struct OpenComponent {
+    name: String,
+    /// line number of its BEGIN
+    begin: usize,
+    /// number of children so far by name, for errors
+    children: HashMap<String, usize>,
+}
 
-            match name.as_str() {
-                "BEGIN" => {
-                    let comp = Self::_from_ics(&cl.value, lines)?;
-                    comps.insert(cl.value, comp);
-                },
-                "END" => {
-                    if cl.value != component_name {
-                        return Err(anyhow!("Unexpected END in component!"));
+struct Parser<'a, L> {
+    lines: L,
+    peeked: Option<(usize, Cow<'a, str>)>,
+    tzs: ICalTimeZones,
+    lenient: bool,
+    keep_raw: bool,
+    /// physical lines of the last content line, if keep_raw
+    raw: String,
+    warnings: Vec<ParseError>,
+    /// ex. ["VCALENDAR", "VEVENT[3]"], for errors
+    path: Vec<String>,
+    open: Vec<OpenComponent>,
+    /// an ancestor's END found before its children's, in lenient mode
+    pending_end: Option<String>,
+}
+
+impl<'a, L: Iterator<Item = (usize, Cow<'a, str>)>> Parser<'a, L> {
+    fn new(lines: L, tzs: ICalTimeZones, options: &ICalParseOptions) -> Self {
+        Self {
+            lines,
+            peeked: None,
+            tzs,
+            lenient: options.lenient,
+            keep_raw: options.keep_raw,
+            raw: String::new(),
+            warnings: Vec::new(),
+            path: Vec::new(),
+            open: Vec::new(),
+            pending_end: None,
+        }
+    }
+
+    fn next_line(&mut self) -> Option<(usize, Cow<'a, str>)> {
+        self.peeked.take().or_else(|| self.lines.next())
+    }
+
+    /// RFC 5545 3.1: next content line with the line number it starts on
+    /// (folded lines continue with a space or tab)
+    fn next_unfolded(&mut self) -> Option<(usize, Cow<'a, str>)> {
+        let (index, mut line) = self.next_line()?;
+        if self.keep_raw {
+            self.raw.clear();
+            self.raw.push_str(&line);
+        }
+        loop {
+            match self.next_line() {
+                Some((_, next)) if next.starts_with(' ') || next.starts_with('\t') => {
+                    line.to_mut().push_str(&next[1..]);
+                    if self.keep_raw {
+                        self.raw.push_str(CRLF);
+                        self.raw.push_str(&next);
                     }
-                    break
                 },
+                next => {
+                    self.peeked = next;
+                    return Some((index + 1, line))
+                }
+            }
+        }
+    }
+
+    /// next non-blank content line
+    fn next_content_line(&mut self) -> Option<(usize, Cow<'a, str>)> {
+        while let Some((number, line)) = self.next_unfolded() {
+            if !line.trim().is_empty() {
+                return Some((number, line))
+            }
+        }
+        None
+    }
+
+    /// finds the next top-level BEGIN, None at the end of the input
+    fn next_begin(&mut self, expected: Option<&str>) -> Result<Option<(usize, String)>, ParseError> {
+        let message = match expected {
+            Some(expected) => format!("ICal must start with BEGIN:{expected}"),
+            None => "Expected BEGIN".to_string(),
+        };
+        while let Some((number, line)) = self.next_content_line() {
+            let name = match ContentLine::parse(&line) {
+                Ok(cl) if cl.name == "BEGIN" => cl.value,
                 _ => {
-                    let prop = ICalProperty::from_content_line(cl)?;
-                    props.insert(name, prop);
+                    self.fail(ParseError::new(message.clone(), &line).at_line(number, &[]))?;
+                    continue
                 }
+            };
+            return match expected {
+                Some(expected) if !name.eq_ignore_ascii_case(expected) => {
+                    Err(ParseError::new(message, &line).at_line(number, &[]))
+                },
+                Some(expected) => Ok(Some((number, expected.to_string()))),
+                None => Ok(Some((number, name.to_ascii_uppercase()))),
             }
         }
+        Ok(None)
+    }
 
-        Ok(Self {
-            props, comps
-        })
+    /// parses the next top-level component, None at the end of the input
+    fn parse_root(&mut self, expected: Option<&str>) -> Result<Option<ICalComponent>, ParseError> {
+        let Some((number, name)) = self.next_begin(expected)? else {
+            return Ok(None)
+        };
+        self.path = vec![name.clone()];
+        self.parse_component(&name, number).map(Some)
     }
-}
 
-fn unfold_line(line: &str, lines: &mut Peekable<Lines>) -> String {
-    let mut line = line.to_string();
-    while next_line_folded(lines) {
-        let mut next_line = lines.next().unwrap().to_string();
-        next_line.remove(0);
-        line.push_str(&next_line);
+    /// content after the component is reported, not dropped
+    fn parse_trailing(&mut self, component_name: &str) -> Result<(), ParseError> {
+        if let Some((number, line)) = self.next_content_line() {
+            let error = ParseError::new(format!("Unexpected content after END:{component_name}"), &line)
+                .at_line(number, &[]);
+            self.fail(error)?;
+        }
+        Ok(())
+    }
+
+    /// in lenient mode the error becomes a warning
+    fn fail(&mut self, error: ParseError) -> Result<(), ParseError> {
+        if !self.lenient {
+            return Err(error)
+        }
+        self.warnings.push(error);
+        Ok(())
+    }
+
+    fn current(&mut self) -> &mut OpenComponent {
+        self.open.last_mut().expect("no open component")
+    }
+
+    fn missing_end(&mut self) -> ParseError {
+        let current = self.current();
+        let error = ParseError::new(format!("Missing END:{}", current.name), &format!("BEGIN:{}", current.name));
+        let begin = current.begin;
+        error.at_line(begin, &self.path)
+    }
+
+    /// starts a component, it must be ended with end_component
+    fn begin_component(&mut self, component_name: &str, begin: usize) {
+        self.open.push(OpenComponent {
+            name: component_name.to_string(),
+            begin,
+            children: HashMap::new(),
+        });
+    }
+
+    fn end_component(&mut self) {
+        self.open.pop();
+    }
+
+    /// parses the component's lines up to its END, begin is the line number of its BEGIN
+    fn parse_component(&mut self, component_name: &str, begin: usize) -> Result<ICalComponent, ParseError> {
+        self.begin_component(component_name, begin);
+        let result = self.parse_lines();
+        self.end_component();
+        result
+    }
+
+    fn parse_lines(&mut self) -> Result<ICalComponent, ParseError> {
+        let mut comp = ICalComponent::named(&self.current().name);
+        loop {
+            match self.step(&mut comp)? {
+                Step::Continue => {},
+                Step::Child(child) => {
+                    comp.comps.insert(child.name.clone(), child);
+                    if self.close_pending() {
+                        return Ok(comp)
+                    }
+                },
+                Step::End => return Ok(comp),
+            }
+        }
+    }
+
+    /// after a child ended, whether an ancestor's END ends this component too
+    fn close_pending(&mut self) -> bool {
+        let Some(end) = self.pending_end.take() else {
+            return false
+        };
+        if end != self.current().name {
+            let error = self.missing_end();
+            self.warnings.push(error);
+            self.pending_end = Some(end);
+        }
+        true
+    }
+
+    /// parses the next content line of the innermost open component into comp
+    fn step(&mut self, comp: &mut ICalComponent) -> Result<Step, ParseError> {
+        let Some((number, line)) = self.next_unfolded() else {
+            let error = self.missing_end();
+            self.fail(error)?;
+            return Ok(Step::End)
+        };
+        if self.lenient && line.trim().is_empty() {
+            return Ok(Step::Continue)
+        }
+        let cl = match ContentLine::parse(&line) {
+            Ok(cl) => cl,
+            Err(e) => {
+                self.fail(e.at_line(number, &self.path))?;
+                comp.raw_lines.push(line.into_owned());
+                return Ok(Step::Continue)
+            }
+        };
+        let name = cl.name.clone();
+        let value_offset = line.len() - cl.value.len();
+
+        match name.as_str() {
+            "BEGIN" => {
+                //names are case-insensitive (RFC 5545 2), keep them uppercase like property names
+                let name = cl.value.to_ascii_uppercase();
+                let nth = self.current().children.entry(name.clone()).or_default();
+                *nth += 1;
+                let nth = *nth;
+                self.path.push(format!("{name}[{nth}]"));
+                let child = self.parse_component(&name, number);
+                self.path.pop();
+                Ok(Step::Child(child?))
+            },
+            "END" => {
+                let component_name = self.current().name.clone();
+                if cl.value.eq_ignore_ascii_case(&component_name) {
+                    return Ok(Step::End)
+                }
+                //an ancestor is ending, so this component is missing its END
+                if self.lenient && self.open.iter().any(|open| open.name.eq_ignore_ascii_case(&cl.value)) {
+                    let error = self.missing_end();
+                    self.warnings.push(error);
+                    self.pending_end = Some(cl.value.to_ascii_uppercase());
+                    return Ok(Step::End)
+                }
+                let error = ParseError::new(format!("Expected END:{component_name}"), &line)
+                    .at_rest(&line[value_offset..])
+                    .at_line(number, &self.path);
+                self.fail(error)?;
+                comp.raw_lines.push(line.into_owned());
+                Ok(Step::Continue)
+            },
+            _ => {
+                let mut prop = match ICalProperty::from_content_line(cl.clone(), &self.tzs) {
+                    Ok(prop) => prop,
+                    Err(e) => {
+                        let error = ParseError::new(format!("{e:#}"), &line)
+                            .at_rest(&line[value_offset..])
+                            .at_line(number, &self.path);
+                        self.fail(error)?;
+                        //kept as written, so it's serialized back unchanged
+                        ICalProperty::new(ICalValue::Unknown(ICalUnknown(cl.value)), cl.params)
+                    }
+                };
+                if self.keep_raw {
+                    prop.raw = Some(ICalRawLine::new(&self.raw, &prop, &name));
+                }
+                comp.props.insert(name, prop);
+                Ok(Step::Continue)
+            }
+        }
     }
-    line
 }
 
-fn next_line_folded(lines: &mut Peekable<Lines>) -> bool {
-    if let Some(next_line) = lines.peek() {
-        return next_line.starts_with(' ') || next_line.starts_with('\t')
+/// RFC 6868: ^n is a newline, ^' is a double quote and ^^ is a caret
+/// NOTE: other ^ are left as is
+fn decode_param_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let decoded = match (c, chars.peek()) {
+            ('^', Some('n')) => '\n',
+            ('^', Some('\'')) => '"',
+            ('^', Some('^')) => '^',
+            _ => {
+                result.push(c);
+                continue
+            },
+        };
+        chars.next();
+        result.push(decoded);
     }
-    false
+    result
 }
 
 #[derive(Debug, Clone)]
 pub struct ContentLine {
     pub name: String,
-    pub params: HashMap<String, String>,
+    pub params: ICalParameterMap,
     pub value: String,
 }
 
 impl ContentLine {
     ///RFC5545 3.1: "name *(";" param ) ":" value CRLF"
-    pub fn parse(line: &str) -> anyhow::Result<Self> {
-        let mut params: HashMap<String, String> = HashMap::new();
+    pub fn parse(line: &str) -> Result<Self, ParseError> {
+        let mut params = ICalParameterMap::new();
 
-        let (name, delim, rest) = Self::split_once_at_delim(line, vec![';', ':'])?;
+        let (name, delim, rest) = Self::split_once_at_delim(line, &[';', ':'])
+            .ok_or(ParseError::new("ContentLine missing delimiter!", line).at_rest(""))?;
 
         let value = if delim == ';' {
-            Self::parse_params(rest, &mut params)?
+            Self::parse_params(line, rest, &mut params)?
         } else {
             rest.to_string()
         };
@@ -92,39 +432,183 @@ impl ContentLine {
     /// Recursively read paramters
     /// Example: DIR="text";
     /// Example: DIR=asdfasdf;
-    fn parse_params(rest: &str, params: &mut HashMap<String, String>) -> anyhow::Result<String> {
+    /// Example: MEMBER="mailto:a@example.com","mailto:b@example.com";
+    fn parse_params(line: &str, rest: &str, params: &mut ICalParameterMap) -> Result<String, ParseError> {
+        let error = |message: &str, at: &str| ParseError::new(message, line).at_rest(at);
         let (name, mut rest) = rest.split_once('=')
-            .ok_or(anyhow!("ContentLine Parameter missing =!"))?;
-
-        let value: &str;
-        let delim: char;
-        if let Some(r) = rest.strip_prefix('"') {
-            let (v, _, r) = Self::split_once_at_delim(r, vec!['"'])?;
-            value = &v[0..v.len()];
-            rest = &r[1..];
-            delim = r.chars().next().unwrap();
-        }
-        else {
-            (value, delim, rest) = Self::split_once_at_delim(rest, vec![';', ':'])?;
-        }
+            .ok_or(error("ContentLine Parameter missing =!", rest))?;
 
-        params.insert(name.to_string(), value.to_string());
+        let mut values = Vec::new();
+        let delim = loop {
+            let value: &str;
+            let delim: char;
+            if let Some(r) = rest.strip_prefix('"') {
+                let (v, r) = r.split_once('"')
+                    .ok_or(error("ContentLine Parameter missing closing quote!", rest))?;
+                let mut chars = r.chars();
+                delim = chars.next().ok_or(error("ContentLine missing value!", r))?;
+                (value, rest) = (v, chars.as_str());
+            }
+            else {
+                (value, delim, rest) = Self::split_once_at_delim(rest, &[',', ';', ':'])
+                    .ok_or(error("ContentLine missing delimiter!", ""))?;
+            }
+            values.push(value.to_string());
+            if delim != ',' {
+                break delim
+            }
+        };
+
+        for value in values {
+            params.append(name, &decode_param_value(&value));
+        }
 
         match delim {
-            ';' => Self::parse_params(rest, params),
+            ';' => Self::parse_params(line, rest, params),
             ':' => Ok(rest.to_string()),
-            _ => Err(anyhow!("ContentLine Parameter had unexpected char after value"))
+            _ => {
+                let at = &line[line.len() - rest.len() - delim.len_utf8()..];
+                Err(error("ContentLine Parameter had unexpected char after value", at))
+            }
         }
     }
 
-    fn split_once_at_delim(line: &str, delims: Vec<char>) -> anyhow::Result<(&str, char, &str)> {
-        let delim_pos = line.find(|c| delims.contains(&c))
-            .ok_or(anyhow!("ContentLine missing delimiter!"))?;
-        let delim = line.chars().nth(delim_pos).unwrap();
+    fn split_once_at_delim<'a>(line: &'a str, delims: &[char]) -> Option<(&'a str, char, &'a str)> {
+        let delim_pos = line.find(|c| delims.contains(&c))?;
+        let delim = line[delim_pos..].chars().next().unwrap();
         let (left, right) = line.split_at(delim_pos);
-        Ok((left, delim, &right[1..]))
+        Some((left, delim, &right[delim.len_utf8()..]))
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use crate::{component::{ICalComponent, VALARM, VTIMEZONE, VTODO}, parser::ICalParseOptions, values::unknown::ICalUnknown};
+
+    #[test]
+    fn test_lenient_parse() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nDTSTART:2024XX01\nRRULE:FREQ=FOO;COUNT=1\nGEO:abc;def\nthis is not a content line\n\nEND:VEVENT\nEND:VCALENDAR";
+        assert!(ICalComponent::from_ics(ics).is_err());
 
+        let parsed = ICalComponent::from_ics_with(ics, &ICalParseOptions { lenient: true, ..Default::default() }).unwrap();
+        assert_eq!(parsed.warnings.len(), 4);
+        assert_eq!((parsed.warnings[0].line, parsed.warnings[0].column), (4, Some(9)));
+        assert_eq!(parsed.warnings[3].line, 7);
+        assert_eq!(parsed.warnings[3].path_string(), "VCALENDAR > VEVENT[1]");
 
+        let mut vcal = parsed.component;
+        let vevent = vcal.expect_vevent();
+        assert_eq!(vevent.get_prop("DTSTART").unwrap().get_as::<ICalUnknown>().unwrap().0, "2024XX01");
+        assert_eq!(vevent.raw_lines, vec!["this is not a content line"]);
+        let ics = vcal.to_ics();
+        assert!(ics.contains("\r\nDTSTART:2024XX01\r\nRRULE:FREQ=FOO;COUNT=1\r\nGEO:abc;def\r\n"), "{ics}");
+        assert!(ics.contains("\r\nthis is not a content line\r\nEND:VEVENT"));
+    }
+
+    #[test]
+    fn test_lenient_parse_structure() {
+        let options = ICalParseOptions { lenient: true, ..Default::default() };
+        //VALARM is closed by its parent's END
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nBEGIN:VALARM\nACTION:DISPLAY\nEND:VEVENT\nBEGIN:VTODO\nEND:VTODO\nEND:VCALENDAR";
+        let parsed = ICalComponent::from_ics_with(ics, &options).unwrap();
+        assert_eq!(parsed.warnings.len(), 1);
+        assert_eq!(parsed.warnings[0].message, "Missing END:VALARM");
+        assert_eq!(parsed.warnings[0].line, 4);
+        let mut vcal = parsed.component;
+        assert!(vcal.get_comps(VTODO).is_some());
+        assert!(vcal.expect_vevent().get_comp(VALARM).is_some());
+
+        //stray END is kept and truncated files are closed
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VTODO\nUID:1";
+        let parsed = ICalComponent::from_ics_with(ics, &options).unwrap();
+        let messages: Vec<&str> = parsed.warnings.iter().map(|w| w.message.as_str()).collect();
+        assert_eq!(messages, vec!["Expected END:VEVENT", "Missing END:VEVENT", "Missing END:VCALENDAR"]);
+        let mut vcal = parsed.component;
+        assert_eq!(vcal.expect_vevent().raw_lines, vec!["END:VTODO"]);
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:1\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n\r\nBEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nUID:2\r\nEND:VTODO\r\nEND:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:3\r\nEND:VEVENT\r\n";
+        let mut comps = ICalComponent::from_ics_all(ics).unwrap();
+        let names: Vec<&str> = comps.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["VCALENDAR", "VCALENDAR", "VEVENT"]);
+        assert_eq!(comps[1].1.expect_vtodo().get_uid_value().unwrap(), "2");
+        assert_eq!(comps[2].1.get_uid_value().unwrap(), "3");
+
+        let err = ICalComponent::from_ics(ics).err().unwrap();
+        assert_eq!(err.message, "Unexpected content after END:VCALENDAR");
+        assert_eq!(err.line, 7);
+
+        let err = ICalComponent::from_ics_all("BEGIN:VCALENDAR\nEND:VCALENDAR\ngarbage").err().unwrap();
+        assert_eq!((err.message.as_str(), err.line), ("Expected BEGIN", 3));
+    }
+
+    #[test]
+    fn test_component_name_case() {
+        let ics = "BEGIN:vcalendar\nBEGIN:vtimezone\nTZID:Custom\nbegin:standard\nDTSTART:19700101T000000\nTZOFFSETFROM:+0300\nTZOFFSETTO:+0300\nend:Standard\nEND:VTIMEZONE\nBEGIN:VEVENT\nUID:1\nDTSTART;TZID=Custom:20240101T090000\nEND:vevent\nend:vcalendar";
+        let mut vcal = ICalComponent::from_ics(ics).unwrap();
+        assert_eq!(vcal.name, "VCALENDAR");
+        assert!(vcal.get_comps(VTIMEZONE).unwrap()[0].get_comps("STANDARD").is_some());
+        let dtstart = vcal.expect_vevent().get_dtstart_value().unwrap().left().unwrap().clone();
+        assert_eq!(dtstart.tzid().unwrap(), "Custom");
+        assert!(vcal.to_ics().ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_parse_component() {
+        let ics = "BEGIN:VCARD\nVERSION:4.0\nFN:Jane Doe\nEND:VCARD\n";
+        let (name, mut vcard) = ICalComponent::from_ics_component(ics).unwrap();
+        assert_eq!(name, "VCARD");
+        assert_eq!(vcard.name, "VCARD");
+        assert_eq!(vcard.get_prop("FN").unwrap().get_as::<ICalUnknown>().unwrap().0, "Jane Doe");
+        assert!(ICalComponent::from_ics(ics).is_err());
+
+        let ics = "BEGIN:VEVENT\nUID:1\nEND:VEVENT\nUID:2\n";
+        let err = ICalComponent::from_ics_component(ics).err().unwrap();
+        assert_eq!(err.to_string(), "Unexpected content after END:VEVENT at line 4: UID:2");
+
+        let parsed = ICalComponent::from_ics_component_with(ics, &ICalParseOptions { lenient: true, ..Default::default() }).unwrap();
+        assert_eq!(parsed.name, "VEVENT");
+        assert_eq!(parsed.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_error_location() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nBEGIN:VEVENT\nUID:2\nBEGIN:VALARM\nTRIGGER:-PXYZ\nEND:VALARM\nEND:VEVENT\nEND:VCALENDAR";
+        let err = ICalComponent::from_ics(ics).err().unwrap();
+        assert_eq!(err.line, 8);
+        assert_eq!(err.column, Some(9));
+        assert_eq!(err.content_line, "TRIGGER:-PXYZ");
+        assert_eq!(err.path_string(), "VCALENDAR > VEVENT[2] > VALARM[1]");
+    }
+
+    #[test]
+    fn test_parse_error_params() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY;LANGUAGE:Hi\nEND:VTODO\nEND:VCALENDAR";
+        let err = ICalComponent::from_ics(ics).err().unwrap();
+        assert_eq!((err.line, err.column), (3, Some(9)));
+        assert_eq!(err.message, "ContentLine Parameter missing =!");
+
+        //folded lines report the line they start on
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nSUMMARY;X-A=\"unterminated\n  value:Hi\nEND:VTODO\nEND:VCALENDAR";
+        let err = ICalComponent::from_ics(ics).err().unwrap();
+        assert_eq!((err.line, err.column), (3, Some(13)));
+        assert_eq!(err.content_line, "SUMMARY;X-A=\"unterminated value:Hi");
+    }
+
+    #[test]
+    fn test_parse_error_structure() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VTODO\nEND:VEVENT\nEND:VCALENDAR";
+        let err = ICalComponent::from_ics(ics).err().unwrap();
+        assert_eq!(err.message, "Expected END:VTODO");
+        assert_eq!((err.line, err.column), (3, Some(5)));
+        assert_eq!(err.path_string(), "VCALENDAR > VTODO[1]");
+
+        let ics = "BEGIN:VCALENDAR\nVERSION:2.0\nBEGIN:VEVENT\nUID:1";
+        let err = ICalComponent::from_ics(ics).err().unwrap();
+        assert_eq!(err.message, "Missing END:VEVENT");
+        assert_eq!(err.line, 3);
+        assert_eq!(err.to_string(), "Missing END:VEVENT at line 3 in VCALENDAR > VEVENT[1]: BEGIN:VEVENT");
+    }
+}
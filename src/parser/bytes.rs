@@ -0,0 +1,212 @@
+use std::borrow::Cow;
+
+use crate::component::{ICalComponent, VCALENDAR};
+
+use super::{error::ParseError, ICalParseOptions, ICalParsed};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl ICalComponent {
+    /// Parse a VCALENDAR from bytes, for files that may not be valid UTF-8
+    /// NOTE: use from_ics_bytes_with to see what was replaced
+    pub fn from_ics_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        Ok(Self::from_ics_bytes_with(bytes, &ICalParseOptions::default())?.component)
+    }
+
+    /// Strips a UTF-8 or UTF-16 BOM, decodes lines with a CHARSET parameter (vCalendar 1.0)
+    /// and replaces invalid UTF-8 with U+FFFD, adding a warning for each line replaced
+    pub fn from_ics_bytes_with(bytes: &[u8], options: &ICalParseOptions) -> Result<ICalParsed, ParseError> {
+        if let Some(ics) = decode_utf16(bytes) {
+            return Self::from_ics_with(&ics, options)
+        }
+        let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+        let mut warnings = Vec::new();
+        let lines = decode_lines(bytes, &mut warnings);
+        let lines = lines.iter().map(|(index, line)| (*index, Cow::Borrowed(line.as_str())));
+        let mut parsed = Self::parse_single(lines, Some(VCALENDAR), options)?;
+        warnings.append(&mut parsed.warnings);
+        warnings.sort_by_key(|warning| warning.line);
+        parsed.warnings = warnings;
+        Ok(parsed)
+    }
+}
+
+/// UTF-16 is only detected by its BOM
+fn decode_utf16(bytes: &[u8]) -> Option<String> {
+    let from_bytes: fn([u8; 2]) -> u16 = match bytes.get(..2)? {
+        [0xFF, 0xFE] => u16::from_le_bytes,
+        [0xFE, 0xFF] => u16::from_be_bytes,
+        _ => return None,
+    };
+    let units = bytes[2..].chunks(2).map(|c| from_bytes([c[0], *c.get(1).unwrap_or(&0)]));
+    Some(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect())
+}
+
+/// decodes each physical line (keeping folds for keep_raw) with the 0-based index,
+/// a character split by a fold is moved to the start of the continuation line
+fn decode_lines(bytes: &[u8], warnings: &mut Vec<ParseError>) -> Vec<(usize, String)> {
+    let mut physical: Vec<&[u8]> = bytes.split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .collect();
+    //like str::lines, a final line break doesn't start another line
+    if physical.last().is_some_and(|line| line.is_empty()) {
+        physical.pop();
+    }
+    let is_fold = |line: Option<&&[u8]>| matches!(line.and_then(|line| line.first()), Some(b' ' | b'\t'));
+
+    let mut lines = Vec::with_capacity(physical.len());
+    //of the content line, from its first physical line
+    let mut charset = None;
+    let mut split_char = Vec::new();
+    for (index, line) in physical.iter().enumerate() {
+        let number = index + 1;
+        let line = match index > 0 && is_fold(Some(line)) {
+            false => {
+                let (line_charset, line) = split_charset(line);
+                charset = line_charset.map(|charset| charset.to_ascii_uppercase());
+                if let Some(charset) = charset.as_deref().filter(|charset| !is_supported(charset)) {
+                    let message = format!("Unsupported CHARSET {charset}, read as UTF-8");
+                    warnings.push(ParseError::new(message, &String::from_utf8_lossy(&line)).at_line(number, &[]));
+                }
+                line
+            },
+            true if split_char.is_empty() => Cow::Borrowed(*line),
+            true => {
+                let mut joined = line[..1].to_vec();
+                joined.append(&mut split_char);
+                joined.extend_from_slice(&line[1..]);
+                Cow::Owned(joined)
+            },
+        };
+        let line = match charset.as_deref() {
+            Some("ISO-8859-1") | Some("LATIN1") => line.iter().map(|&b| char::from(b)).collect(),
+            Some("WINDOWS-1252") | Some("CP1252") => line.iter().map(|&b| windows_1252(b)).collect(),
+            _ => decode_utf8(number, &line, is_fold(physical.get(index + 1)), &mut split_char, warnings),
+        };
+        lines.push((index, line));
+    }
+    lines
+}
+
+fn is_supported(charset: &str) -> bool {
+    matches!(charset, "UTF-8" | "US-ASCII" | "ISO-8859-1" | "LATIN1" | "WINDOWS-1252" | "CP1252")
+}
+
+/// replaces invalid UTF-8 with U+FFFD, except a character cut off by a fold
+/// (next_folded), which is left in split_char
+fn decode_utf8(number: usize, line: &[u8], next_folded: bool, split_char: &mut Vec<u8>, warnings: &mut Vec<ParseError>) -> String {
+    let error = match std::str::from_utf8(line) {
+        Ok(line) => return line.to_string(),
+        Err(error) => error,
+    };
+    let valid = error.valid_up_to();
+    if next_folded && error.error_len().is_none() {
+        split_char.extend_from_slice(&line[valid..]);
+        return String::from_utf8_lossy(&line[..valid]).into_owned()
+    }
+    let line = String::from_utf8_lossy(line).into_owned();
+    //the valid prefix is unchanged
+    let error = ParseError::new("Invalid UTF-8 replaced with U+FFFD", &line)
+        .at_rest(&line[valid..])
+        .at_line(number, &[]);
+    warnings.push(error);
+    line
+}
+
+/// vCalendar 1.0: the CHARSET parameter is removed since the line becomes UTF-8
+fn split_charset(line: &[u8]) -> (Option<String>, Cow<'_, [u8]>) {
+    const CHARSET: &[u8] = b";CHARSET=";
+    let head = &line[..value_start(line)];
+    let Some(start) = head.windows(CHARSET.len()).position(|w| w.eq_ignore_ascii_case(CHARSET)) else {
+        return (None, Cow::Borrowed(line))
+    };
+    let value = start + CHARSET.len();
+    let end = head[value..].iter().position(|&b| b == b';').map_or(head.len(), |i| value + i);
+    let charset = String::from_utf8_lossy(&head[value..end]).trim_matches('"').to_string();
+    let mut rest = line[..start].to_vec();
+    rest.extend_from_slice(&line[end..]);
+    (Some(charset), Cow::Owned(rest))
+}
+
+/// position of the ":" before the value
+fn value_start(line: &[u8]) -> usize {
+    let mut quoted = false;
+    for (i, &b) in line.iter().enumerate() {
+        match b {
+            b'"' => quoted = !quoted,
+            b':' if !quoted => return i,
+            _ => {},
+        }
+    }
+    line.len()
+}
+
+/// Windows-1252 is ISO-8859-1 except for 0x80-0x9F
+fn windows_1252(b: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+        '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+    ];
+    match b {
+        0x80..=0x9F => HIGH[(b - 0x80) as usize],
+        _ => char::from(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{component::ICalComponent, parser::ICalParseOptions};
+
+    #[test]
+    fn test_parse_bytes_bom() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:Café\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        let mut utf8 = b"\xEF\xBB\xBF".to_vec();
+        utf8.extend_from_slice(ics.as_bytes());
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(ics.encode_utf16().flat_map(u16::to_le_bytes));
+
+        for bytes in [utf8, utf16] {
+            let mut vcal = ICalComponent::from_ics_bytes(&bytes).unwrap();
+            assert_eq!(vcal.expect_vtodo().get_summary_value().unwrap(), "Café");
+        }
+        assert!(ICalComponent::from_ics("\u{FEFF}BEGIN:VCALENDAR\nEND:VCALENDAR").is_ok());
+    }
+
+    #[test]
+    fn test_parse_bytes_charset() {
+        let bytes = b"BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY;CHARSET=ISO-8859-1;LANGUAGE=fr:R\xE9union\r\nDESCRIPTION;CHARSET=windows-1252:\x93Quoted\x94 \x80\r\nLOCATION:caf\xC3\r\n \xA9\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let mut vcal = ICalComponent::from_ics_bytes(bytes).unwrap();
+        let vevent = vcal.expect_vevent();
+        assert_eq!(vevent.get_summary_value().unwrap(), "Réunion");
+        assert_eq!(vevent.get_description_value().unwrap(), "\u{201C}Quoted\u{201D} \u{20AC}");
+        assert_eq!(vevent.get_location_value().unwrap(), "café");
+        assert!(vcal.to_ics().contains("\r\nSUMMARY;LANGUAGE=fr:Réunion\r\n"));
+    }
+
+    #[test]
+    fn test_parse_bytes_invalid_utf8() {
+        let bytes = b"BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nDESCRIPTION:bad \xFF byte\nSUMMARY;CHARSET=EBCDIC:x\nEND:VEVENT\nEND:VCALENDAR";
+        let parsed = ICalComponent::from_ics_bytes_with(bytes, &ICalParseOptions::default()).unwrap();
+        assert_eq!(parsed.warnings.len(), 2);
+        assert_eq!((parsed.warnings[0].line, parsed.warnings[0].column), (4, Some(17)));
+        assert_eq!(parsed.warnings[1].message, "Unsupported CHARSET EBCDIC, read as UTF-8");
+
+        let mut vcal = parsed.component;
+        assert_eq!(vcal.expect_vevent().get_description_value().unwrap(), "bad \u{FFFD} byte");
+    }
+
+    #[test]
+    fn test_parse_bytes_keep_raw() {
+        let bytes = b"BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDESCRIPTION:Folded at an\r\n  odd place\r\nLOCATION:caf\xC3\r\n \xA9 \xC3\r\n\t\xA9\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let parsed = ICalComponent::from_ics_bytes_with(bytes, &ICalParseOptions { keep_raw: true, ..Default::default() }).unwrap();
+        assert!(parsed.warnings.is_empty());
+        let mut vcal = parsed.component;
+        assert_eq!(vcal.expect_vevent().get_location_value().unwrap(), "café é");
+        let ics = vcal.to_ics();
+        assert!(ics.contains("\r\nDESCRIPTION:Folded at an\r\n  odd place\r\n"), "{ics}");
+        //the split characters move to the continuation lines
+        assert!(ics.contains("\r\nLOCATION:caf\r\n é \r\n\té\r\n"), "{ics}");
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+/// An error parsing an ICalendar string, with where it happened
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    /// 1-based physical line number (the first line of a folded content line)
+    pub line: usize,
+    /// 1-based character column in the unfolded content line
+    pub column: Option<usize>,
+    /// the unfolded content line
+    pub content_line: String,
+    /// ex. ["VCALENDAR", "VEVENT[3]", "VALARM[1]"], indexes count components of the same name
+    pub path: Vec<String>,
+}
+
+impl ParseError {
+    pub(crate) fn new(message: impl Into<String>, content_line: &str) -> Self {
+        Self {
+            message: message.into(),
+            line: 0,
+            column: None,
+            content_line: content_line.to_string(),
+            path: Vec::new(),
+        }
+    }
+
+    /// column of the start of rest, a suffix of the content line
+    pub(crate) fn at_rest(mut self, rest: &str) -> Self {
+        let offset = self.content_line.len() - rest.len();
+        self.column = Some(self.content_line[..offset].chars().count() + 1);
+        self
+    }
+
+    pub(crate) fn at_line(mut self, line: usize, path: &[String]) -> Self {
+        self.line = line;
+        self.path = path.to_vec();
+        self
+    }
+
+    /// ex. "VCALENDAR > VEVENT[3] > VALARM[1]"
+    pub fn path_string(&self) -> String {
+        self.path.join(" > ")
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)?;
+        if let Some(column) = self.column {
+            write!(f, ", column {column}")?;
+        }
+        if !self.path.is_empty() {
+            write!(f, " in {}", self.path_string())?;
+        }
+        write!(f, ": {}", self.content_line)
+    }
+}
+
+impl std::error::Error for ParseError {}
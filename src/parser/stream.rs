@@ -0,0 +1,194 @@
+use std::{borrow::Cow, io::BufRead};
+
+use crate::{component::{ICalComponent, VCALENDAR, VTIMEZONE}, timezone::{ICalTimeZones, VTimeZone}};
+
+use super::{error::ParseError, ICalParseOptions, Parser, Step};
+
+/// Reads components one at a time, see [ICalComponent::stream_ics]
+pub struct ICalStream<R> {
+    parser: Parser<'static, ReaderLines<R>>,
+    /// properties of the latest VCALENDAR (its components are streamed)
+    calendar: Option<ICalComponent>,
+    in_calendar: bool,
+    done: bool,
+}
+
+impl ICalComponent {
+    /// Parse from a reader, yielding the components of each VCALENDAR
+    /// (and any top-level components that aren't VCALENDARs) as they end,
+    /// so only one component is in memory at a time
+    /// NOTE: TZIDs only resolve against VTIMEZONEs that come before their use
+    pub fn stream_ics<R: BufRead>(reader: R) -> ICalStream<R> {
+        Self::stream_ics_with(reader, &ICalParseOptions::default())
+    }
+
+    pub fn stream_ics_with<R: BufRead>(reader: R, options: &ICalParseOptions) -> ICalStream<R> {
+        let lines = ReaderLines { reader, index: 0, error: None };
+        ICalStream {
+            parser: Parser::new(lines, ICalTimeZones::default(), options),
+            calendar: None,
+            in_calendar: false,
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> ICalStream<R> {
+    /// properties of the latest VCALENDAR (ex. VERSION and PRODID)
+    pub fn calendar(&self) -> Option<&ICalComponent> {
+        self.calendar.as_ref()
+    }
+
+    /// errors skipped in lenient mode so far
+    pub fn warnings(&self) -> &[ParseError] {
+        &self.parser.warnings
+    }
+
+    fn read_next(&mut self) -> Result<Option<ICalComponent>, ParseError> {
+        loop {
+            let calendar = match &mut self.calendar {
+                Some(calendar) if self.in_calendar => calendar,
+                _ => {
+                    let Some((number, name)) = self.parser.next_begin(None)? else {
+                        return Ok(None)
+                    };
+                    self.parser.path = vec![name.clone()];
+                    if !name.eq_ignore_ascii_case(VCALENDAR) {
+                        return self.parser.parse_component(&name, number).map(Some)
+                    }
+                    self.parser.begin_component(VCALENDAR, number);
+                    self.parser.path = vec![VCALENDAR.to_string()];
+                    self.calendar = Some(ICalComponent::named(VCALENDAR));
+                    self.in_calendar = true;
+                    continue
+                }
+            };
+            match self.parser.step(calendar)? {
+                Step::Continue => {},
+                Step::Child(child) => {
+                    if self.parser.close_pending() {
+                        self.end_calendar();
+                    }
+                    if child.name == VTIMEZONE {
+                        if let Ok(vtz) = VTimeZone::from_component(&child) {
+                            self.parser.tzs.insert(vtz);
+                        }
+                    }
+                    return Ok(Some(child))
+                },
+                Step::End => self.end_calendar(),
+            }
+        }
+    }
+
+    fn end_calendar(&mut self) {
+        self.parser.end_component();
+        self.in_calendar = false;
+    }
+}
+
+impl<R: BufRead> Iterator for ICalStream<R> {
+    type Item = Result<(String, ICalComponent), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None
+        }
+        let result = self.read_next();
+        //a read error ends the input early, so it comes before any parse error
+        let result = match self.parser.lines.error.take() {
+            Some(error) => Err(error),
+            None => result,
+        };
+        match result {
+            Ok(Some(comp)) => Some(Ok((comp.name.clone(), comp))),
+            Ok(None) => {
+                self.done = true;
+                None
+            },
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            },
+        }
+    }
+}
+
+/// physical lines with their 0-based index, stopping at the first read error
+struct ReaderLines<R> {
+    reader: R,
+    index: usize,
+    error: Option<ParseError>,
+}
+
+impl<R: BufRead> Iterator for ReaderLines<R> {
+    type Item = (usize, Cow<'static, str>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error.is_some() {
+            return None
+        }
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                //CRLF or LF
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
+                self.index += 1;
+                Some((self.index - 1, Cow::Owned(line)))
+            },
+            Err(e) => {
+                self.error = Some(ParseError::new(format!("Failed to read: {e}"), "").at_line(self.index + 1, &[]));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, Cursor};
+
+    use crate::component::{ICalComponent, VEVENT, VTIMEZONE};
+
+    #[test]
+    fn test_stream() {
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTIMEZONE\r\nTZID:Custom\r\nBEGIN:STANDARD\r\nDTSTART:19700101T000000\r\nTZOFFSETFROM:+0300\r\nTZOFFSETTO:+0300\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\nBEGIN:VEVENT\r\nUID:1\r\nSUMMARY:A long summary that is fol\r\n ded\r\nDTSTART;TZID=Custom:20240101T090000\r\nEND:VEVENT\r\nEND:VCALENDAR\r\nBEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:2\nEND:VEVENT\nEND:VCALENDAR\n";
+        //a tiny buffer splits lines and folds across reads
+        let mut stream = ICalComponent::stream_ics(BufReader::with_capacity(4, Cursor::new(ics)));
+
+        let (name, vtimezone) = stream.next().unwrap().unwrap();
+        assert_eq!((name.as_str(), vtimezone.name.as_str()), (VTIMEZONE, VTIMEZONE));
+        assert_eq!(stream.calendar().unwrap().get_version_value().unwrap(), "2.0");
+
+        let (name, vevent) = stream.next().unwrap().unwrap();
+        assert_eq!(name, VEVENT);
+        assert_eq!(vevent.get_summary_value().unwrap(), "A long summary that is folded");
+        let dtstart = vevent.get_dtstart_value().unwrap().left().unwrap();
+        assert_eq!(dtstart.tzid().unwrap(), "Custom");
+
+        let (_, vevent) = stream.next().unwrap().unwrap();
+        assert_eq!(vevent.get_uid_value().unwrap(), "2");
+        assert!(stream.next().is_none());
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let ics = "BEGIN:VCALENDAR\nBEGIN:VEVENT\nUID:1\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART:bad\nEND:VEVENT\nEND:VCALENDAR";
+        let results: Vec<_> = ICalComponent::stream_ics(Cursor::new(ics)).collect();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        let err = results[1].as_ref().err().unwrap();
+        assert_eq!((err.line, err.path_string().as_str()), (6, "VCALENDAR > VEVENT[2]"));
+
+        //invalid UTF-8 is a read error
+        let ics: &[u8] = b"BEGIN:VCALENDAR\nBEGIN:VEVENT\nSUMMARY:\xff\nEND:VEVENT\nEND:VCALENDAR";
+        let err = ICalComponent::stream_ics(ics).next().unwrap().err().unwrap();
+        assert_eq!(err.line, 3);
+    }
+}
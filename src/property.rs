@@ -1,55 +1,85 @@
-use std::collections::HashMap;
 use anyhow::anyhow;
 
-use crate::{parser::ContentLine, values::{text::ICalText, GetEitherFromICalValue, GetFromICalValue, ICalValue, ICalValueTrait}};
+use crate::{parser::ContentLine, timezone::ICalTimeZones, values::{GetEitherFromICalValue, GetFromICalValue, ICalValue, ICalValueTrait}};
 
 #[derive(Clone)]
-
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ICalProperty {
     pub value: ICalValue,
     pub params: ICalParameterMap,
+    /// the original content line, see [crate::parser::ICalParseOptions::keep_raw]
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    pub raw: Option<ICalRawLine>,
+}
+
+/// A property's content line as it was parsed
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ICalRawLine {
+    /// the physical (folded) lines joined with CRLF
+    pub text: String,
+    /// the property serialized right after parsing, a different line now means it was modified
+    serialized: String,
+}
+
+impl ICalRawLine {
+    pub(crate) fn new(text: &str, prop: &ICalProperty, name: &str) -> Self {
+        let mut serialized = String::new();
+        prop.push_line(&mut serialized, name, false);
+        Self { text: text.to_string(), serialized }
+    }
+
+    /// whether the property still serializes to line
+    pub(crate) fn matches(&self, line: &str) -> bool {
+        self.serialized == line
+    }
 }
 
-pub type ICalParameterMap = HashMap<String, String>;
+pub use crate::parameter::ICalParameterMap;
 
 
 impl ICalProperty {
     pub fn new(value: ICalValue, params: ICalParameterMap) -> Self {
-        ICalProperty { value, params }
+        ICalProperty { value, params, raw: None }
     }
 
     /// creates a property with value and no parameters
     pub fn from_value(value: ICalValue) -> Self {
-        Self::new(value, HashMap::new())
+        Self::new(value, ICalParameterMap::new())
     }
 
     pub fn set_param(&mut self, name: &str, value: &str) -> &mut Self {
-        self.params.insert(name.to_string(), value.to_string());
+        self.params.insert(name, value);
         self
     }
 
-    pub fn get_param(&self, name: &str) -> Option<&String> {
+    /// returns the first value of the parameter
+    pub fn get_param(&self, name: &str) -> Option<&str> {
         self.params.get(name)
     }
 
-    pub(crate) fn from_content_line(cl: ContentLine) -> anyhow::Result<Self> {
+    pub(crate) fn from_content_line(cl: ContentLine, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         let value = match cl.params.get("VALUE") {
-            Some(v) => ICalValue::from_value_param(v, &cl.value, &cl.params)?,
-            None => ICalValue::from_default(&cl.name, &cl.value, &cl.params)?,
+            Some(v) => ICalValue::from_value_param(v, &cl.value, &cl.params, tzs)?,
+            None => ICalValue::from_default(&cl.name, &cl.value, &cl.params, tzs)?,
         };
         Ok(Self::new(value, cl.params))
     }
 
     /// Try to convert property value to a specified type
-    /// NOTE: starting value must be of type ICalText
+    /// NOTE: starting value must be of type ICalUnknown or ICalText
     /// (X and IANA props will be if not already converted)
     pub fn convert_value<T>(&mut self) -> anyhow::Result<&mut Self>
     where
         T: ICalValueTrait,
         ICalValue: From<T>,
     {
-        let value: &ICalText = self.get_as().ok_or(anyhow!("Value must be ICalText to convert!"))?;
-        let new_value = T::parse(value, &self.params)?;
+        //parse the original (escaped) value
+        let value = match &self.value {
+            ICalValue::Unknown(_) | ICalValue::Text(_) => self.value.serialize(),
+            _ => return Err(anyhow!("Value must be ICalUnknown or ICalText to convert!")),
+        };
+        let new_value = T::parse(&value, &self.params)?;
         self.value = new_value.into();
         Ok(self)
     }
@@ -66,23 +96,3 @@ impl ICalProperty {
     }
 }
 
-
-// macro_rules! params {
-//     () => {
-//         {
-//             let map: ICalParameterMap = HashMap::new();
-//             map
-//         }
-//     };
-//     ($($key:ident = $value:expr),+ $(,)?) => {
-//         {
-//             let mut map: ICalParameterMap = HashMap::new();
-//             $(
-//                 map.insert(stringify!($key).to_string(), stringify!($value).to_string());
-//             )+
-//             map
-//         }
-//     };
-// }
-//
-// pub use params;
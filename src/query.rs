@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use either::Either;
+
+use crate::{
+    component::{ICalComponent, VEVENT, VFREEBUSY, VJOURNAL, VTODO},
+    generator::GetPropError,
+    parameter::AlarmTriggerRelationship,
+    recurrence::ICalInstance,
+    values::{datetime::{resolve_local, ICalDateTime}, period::ICalPeriod, recur::DateOrDateTime, ICalValue},
+};
+
+/// A CalDAV time range (RFC 4791 9.9), either end may be open
+#[derive(Clone, Debug, PartialEq)]
+pub struct ICalTimeRange {
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    /// timezone for floating DATE and DATE-TIME values (CALDAV:calendar-timezone)
+    pub floating_tz: Tz,
+}
+
+impl ICalTimeRange {
+    pub fn new(start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) -> Self {
+        Self { start, end, floating_tz: Tz::UTC }
+    }
+
+    pub fn floating_tz(mut self, tz: Tz) -> Self {
+        self.floating_tz = tz;
+        self
+    }
+
+    /// converts to UTC, floating values are in floating_tz
+    pub fn to_utc(&self, value: &DateOrDateTime) -> DateTime<Utc> {
+        match value {
+            DateOrDateTime::DateTime(ICalDateTime::Zoned(dt)) => dt.with_timezone(&Utc),
+            local => resolve_local(&self.floating_tz, &local.naive_local()).with_timezone(&Utc),
+        }
+    }
+
+    /// RFC 4791 9.9: whether a DATE or DATE-TIME property value is in the range (start <= value < end),
+    /// or a PERIOD overlaps it. Lists match if any value does, other types never match
+    pub fn matches_value(&self, value: &ICalValue) -> bool {
+        let in_range = |value: DateOrDateTime| {
+            let value = self.to_utc(&value);
+            self.starts_at_or_before(&value) && self.ends_after(&value)
+        };
+        match value {
+            ICalValue::Date(d) => in_range(DateOrDateTime::Date(*d)),
+            ICalValue::DateList(ds) => ds.iter().any(|d| in_range(DateOrDateTime::Date(*d))),
+            ICalValue::DateTime(dt) => in_range(DateOrDateTime::DateTime(dt.clone())),
+            ICalValue::DateTimeList(dts) => dts.iter().any(|dt| in_range(DateOrDateTime::DateTime(dt.clone()))),
+            ICalValue::Period(p) => self.overlaps_period(p),
+            ICalValue::PeriodList(ps) => ps.iter().any(|p| self.overlaps_period(p)),
+            _ => false,
+        }
+    }
+
+    fn overlaps_period(&self, period: &ICalPeriod) -> bool {
+        let start = self.to_utc(&DateOrDateTime::DateTime(period.start.clone()));
+        let end = self.to_utc(&DateOrDateTime::DateTime(period.calc_end()));
+        self.starts_before(&end) && self.ends_after(&start)
+    }
+
+    /// start < value (open start is always before)
+    fn starts_before(&self, value: &DateTime<Utc>) -> bool {
+        self.start.is_none_or(|start| start < *value)
+    }
+
+    /// start <= value
+    fn starts_at_or_before(&self, value: &DateTime<Utc>) -> bool {
+        self.start.is_none_or(|start| start <= *value)
+    }
+
+    /// end > value (open end is always after)
+    fn ends_after(&self, value: &DateTime<Utc>) -> bool {
+        self.end.is_none_or(|end| end > *value)
+    }
+
+    /// end >= value
+    fn ends_at_or_after(&self, value: &DateTime<Utc>) -> bool {
+        self.end.is_none_or(|end| end >= *value)
+    }
+}
+
+impl ICalComponent {
+    /// Returns all VEVENT, VTODO, VJOURNAL & VFREEBUSY instances (in this VCALENDAR) overlapping the range,
+    /// ordered by RECURRENCE-ID. Recurring components are expanded lazily, see [ICalComponent::instances]
+    /// VTODOs without DTSTART have a single instance at DUE, COMPLETED, CREATED or DTSTAMP
+    /// (or the start or end of the range when they have none, no instance if both are open),
+    /// VFREEBUSYs without DTSTART span their FREEBUSY periods
+    /// NOTE: infinite recurrences never end for ranges without an end, bound them with take or take_while
+    pub fn query_time_range<'a>(&'a self, range: &'a ICalTimeRange) -> Result<impl Iterator<Item = ICalInstance<'a>> + 'a, GetPropError> {
+        let mut sets = Vec::new();
+        for comp_name in [VEVENT, VTODO, VJOURNAL, VFREEBUSY] {
+            for (master, overrides) in self.recurrence_sets(comp_name) {
+                sets.push(master.instances_in_range(comp_name, &overrides, range)?.peekable());
+            }
+        }
+        //merge the sets, each is already ordered
+        Ok(std::iter::from_fn(move || {
+            let (next, _) = sets.iter_mut().enumerate()
+                .filter_map(|(i, set)| Some((i, range.to_utc(&set.peek()?.recurrence_id))))
+                .min_by_key(|(_, rid)| *rid)?;
+            sets[next].next()
+        }))
+    }
+
+    /// whether this VEVENT, VTODO, VJOURNAL or VFREEBUSY overlaps the range (RFC 4791 9.9)
+    /// Recurring components match if any instance does
+    pub fn matches_time_range(&self, comp_name: &str, overrides: &[&ICalComponent], range: &ICalTimeRange) -> Result<bool, GetPropError> {
+        if comp_name == VTODO && !self.props.contains_key("DTSTART") {
+            return Ok(self.vtodo_undated_overlaps(range))
+        }
+        if comp_name == VFREEBUSY && !self.props.contains_key("DTSTART") {
+            return Ok(self.freebusy_overlaps(range))
+        }
+        Ok(self.instances_in_range(comp_name, overrides, range)?.next().is_some())
+    }
+
+    /// whether this VALARM triggers in the range (RFC 4791 9.9), REPEATs included
+    /// Relative triggers are taken from every instance of parent that isn't overridden
+    /// (overrides have their own VALARMs)
+    pub fn alarm_matches_time_range(&self, parent: &ICalComponent, overrides: &[&ICalComponent], range: &ICalTimeRange) -> Result<bool, GetPropError> {
+        let repeat = self.get_repeat_value().map_or(0, |repeat| (*repeat).max(0));
+        let interval = self.get_duration_value().map(|d| d.to_time_delta()).unwrap_or_default();
+        let triggers = |first: DateTime<Utc>| (0..=repeat).map(move |n| first + interval * n);
+        let in_range = |trigger: DateTime<Utc>| range.starts_at_or_before(&trigger) && range.ends_after(&trigger);
+
+        let offset = match self.get_trigger_value()? {
+            Either::Right(dt) => return Ok(triggers(range.to_utc(&DateOrDateTime::DateTime(dt.clone()))).any(in_range)),
+            Either::Left(duration) => duration.to_time_delta(),
+        };
+        let related = self.props.get("TRIGGER").and_then(|trigger| trigger.params.related());
+        let from_end = related == Some(AlarmTriggerRelationship::End);
+        for instance in parent.instances(overrides)? {
+            if instance.overridden_by.is_some() {
+                continue
+            }
+            let first = range.to_utc(if from_end { &instance.end } else { &instance.start }) + offset;
+            if triggers(first).any(in_range) {
+                return Ok(true)
+            }
+            if range.end.is_some_and(|end| first >= end) {
+                break
+            }
+        }
+        Ok(false)
+    }
+
+    /// groups sub-components by UID into masters and their overrides
+    /// overrides without a master are returned as masters
+    fn recurrence_sets(&self, comp_name: &str) -> Vec<(&ICalComponent, Vec<&ICalComponent>)> {
+        let Some(comps) = self.comps.get_vec(comp_name) else {
+            return vec![]
+        };
+        let mut overrides: HashMap<&str, Vec<&ICalComponent>> = HashMap::new();
+        for comp in comps.iter().filter(|c| c.props.contains_key("RECURRENCE-ID")) {
+            let uid = comp.get_uid_value().map(|u| u.as_str()).unwrap_or_default();
+            overrides.entry(uid).or_default().push(comp);
+        }
+        let mut sets: Vec<(&ICalComponent, Vec<&ICalComponent>)> = comps.iter()
+            .filter(|c| !c.props.contains_key("RECURRENCE-ID"))
+            .map(|master| {
+                let uid = master.get_uid_value().map(|u| u.as_str()).unwrap_or_default();
+                (master, overrides.remove(uid).unwrap_or_default())
+            })
+            .collect();
+        sets.extend(overrides.into_values().flatten().map(|orphan| (orphan, vec![])));
+        sets
+    }
+
+    fn instances_in_range<'a>(&'a self, comp_name: &'a str, overrides: &[&'a ICalComponent], range: &'a ICalTimeRange) -> Result<Box<dyn Iterator<Item = ICalInstance<'a>> + 'a>, GetPropError> {
+        if comp_name == VTODO && !self.props.contains_key("DTSTART") {
+            return Ok(Box::new(self.vtodo_undated_instance(range).filter(|_| self.vtodo_undated_overlaps(range)).into_iter()))
+        }
+        if comp_name == VFREEBUSY && !self.props.contains_key("DTSTART") {
+            return Ok(Box::new(self.freebusy_instance(range).filter(|_| self.freebusy_overlaps(range)).into_iter()))
+        }
+        if !self.props.contains_key("DTSTART") {
+            return Ok(Box::new(std::iter::empty()))
+        }
+
+        let last_rid = last_rid(overrides, range);
+        let instances = self.instances(overrides)?
+            .take_while(move |instance| last_rid.is_none_or(|last| range.to_utc(&instance.recurrence_id) <= last))
+            .filter(move |instance| instance_overlaps(comp_name, instance, range));
+        Ok(Box::new(instances))
+    }
+
+    /// RFC 4791 9.9: VTODO without DTSTART
+    fn vtodo_undated_overlaps(&self, range: &ICalTimeRange) -> bool {
+        let get = |name: &str| -> Option<DateTime<Utc>> {
+            match &self.props.get(name)?.value {
+                ICalValue::DateTime(dt) => Some(range.to_utc(&DateOrDateTime::DateTime(dt.clone()))),
+                ICalValue::Date(d) => Some(range.to_utc(&DateOrDateTime::Date(*d))),
+                _ => None,
+            }
+        };
+        if let Some(due) = get("DUE") {
+            return range.starts_before(&due) && range.ends_at_or_after(&due)
+        }
+        match (get("COMPLETED"), get("CREATED")) {
+            (Some(completed), Some(created)) => {
+                (range.starts_at_or_before(&created) || range.starts_at_or_before(&completed))
+                    && (range.ends_at_or_after(&created) || range.ends_at_or_after(&completed))
+            },
+            (Some(completed), None) => range.starts_at_or_before(&completed) && range.ends_at_or_after(&completed),
+            (None, Some(created)) => range.ends_after(&created),
+            (None, None) => true,
+        }
+    }
+
+    /// undated VTODOs match every range (RFC 4791 9.9), so they fall back to its start (or end)
+    /// None if there's no date and the range is open at both ends
+    fn vtodo_undated_instance(&self, range: &ICalTimeRange) -> Option<ICalInstance<'_>> {
+        let date = ["DUE", "COMPLETED", "CREATED", "DTSTAMP"].iter()
+            .find_map(|name| match &self.props.get(name)?.value {
+                ICalValue::DateTime(dt) => Some(DateOrDateTime::DateTime(dt.clone())),
+                ICalValue::Date(d) => Some(DateOrDateTime::Date(*d)),
+                _ => None,
+            })
+            .or_else(|| {
+                let start = range.start.or(range.end)?;
+                Some(DateOrDateTime::DateTime(ICalDateTime::Zoned(start.with_timezone(&Tz::UTC.into()))))
+            })?;
+        Some(ICalInstance {
+            recurrence_id: date.clone(),
+            start: date.clone(),
+            end: date,
+            master: self,
+            overridden_by: None,
+        })
+    }
+
+    /// VFREEBUSY without DTSTART: from its first FREEBUSY period to its last
+    fn freebusy_instance(&self, range: &ICalTimeRange) -> Option<ICalInstance<'_>> {
+        let periods: Vec<&ICalPeriod> = self.props.get_vec("FREEBUSY").into_iter().flatten()
+            .flat_map(|prop| match &prop.value {
+                ICalValue::Period(p) => vec![p],
+                ICalValue::PeriodList(ps) => ps.iter().collect(),
+                _ => vec![],
+            })
+            .collect();
+        let to_utc = |dt: ICalDateTime| (range.to_utc(&DateOrDateTime::DateTime(dt.clone())), dt);
+        let start = periods.iter().map(|p| to_utc(p.start.clone())).min_by_key(|(utc, _)| *utc)?.1;
+        let end = periods.iter().map(|p| to_utc(p.calc_end())).max_by_key(|(utc, _)| *utc)?.1;
+        Some(ICalInstance {
+            recurrence_id: DateOrDateTime::DateTime(start.clone()),
+            start: DateOrDateTime::DateTime(start),
+            end: DateOrDateTime::DateTime(end),
+            master: self,
+            overridden_by: None,
+        })
+    }
+
+    /// RFC 4791 9.9: VFREEBUSY without DTSTART
+    fn freebusy_overlaps(&self, range: &ICalTimeRange) -> bool {
+        self.props.get_vec("FREEBUSY").into_iter().flatten()
+            .any(|prop| matches!(prop.value, ICalValue::Period(_) | ICalValue::PeriodList(_)) && range.matches_value(&prop.value))
+    }
+}
+
+/// the last RECURRENCE-ID that can overlap the range, None if it has no end
+/// NOTE: overrides can move instances earlier, so this is past the end of the range by the largest move
+fn last_rid(overrides: &[&ICalComponent], range: &ICalTimeRange) -> Option<DateTime<Utc>> {
+    let slack = overrides.iter()
+        .filter_map(|o| {
+            let rid: DateOrDateTime = o.get_recurrence_id_value().ok()?.into();
+            let start: DateOrDateTime = o.get_dtstart_value().ok()?.into();
+            Some(range.to_utc(&rid) - range.to_utc(&start))
+        })
+        .fold(TimeDelta::zero(), TimeDelta::max);
+    range.end.map(|end| end + slack)
+}
+
+/// RFC 4791 9.9 for an instance with a start
+fn instance_overlaps(comp_name: &str, instance: &ICalInstance, range: &ICalTimeRange) -> bool {
+    let comp = instance.component();
+    let (start, end) = (range.to_utc(&instance.start), range.to_utc(&instance.end));
+    let has = |name: &str| comp.props.contains_key(name);
+    match comp_name {
+        VTODO if has("DUE") => {
+            (range.starts_before(&end) || range.starts_at_or_before(&start))
+                && (range.ends_after(&start) || range.ends_at_or_after(&end))
+        },
+        VTODO if has("DURATION") => {
+            range.starts_at_or_before(&end) && (range.ends_after(&start) || range.ends_at_or_after(&end))
+        },
+        VTODO => range.starts_at_or_before(&start) && range.ends_after(&start),
+        VFREEBUSY => range.starts_at_or_before(&end) && range.ends_after(&start),
+        //VEVENT & VJOURNAL (DATE values without an end last 1 day)
+        _ if has("DTEND") || end > start => range.starts_before(&end) && range.ends_after(&start),
+        _ => range.starts_at_or_before(&start) && range.ends_after(&start),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::{component::ICalComponent, query::ICalTimeRange};
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.with_ymd_and_hms(y, mo, d, h, 0, 0).unwrap())
+    }
+
+    fn summaries(ics: &str, range: &ICalTimeRange) -> Vec<String> {
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        vcal.query_time_range(range).unwrap()
+            .map(|i| i.component().get_summary_value().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_vevent_time_range() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+SUMMARY:Weekly
+DTSTART:20240101T090000Z
+DTEND:20240101T100000Z
+RRULE:FREQ=WEEKLY
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:Instant
+DTSTART:20240110T000000Z
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:All Day
+DTSTART;VALUE=DATE:20240109
+END:VEVENT
+END:VCALENDAR"#;
+        let range = ICalTimeRange::new(utc(2024, 1, 8, 0), utc(2024, 1, 10, 0));
+        let mut result = summaries(ics, &range);
+        result.sort();
+        assert_eq!(result, vec!["All Day", "Weekly"]);
+
+        let range = ICalTimeRange::new(utc(2024, 1, 10, 0), utc(2024, 1, 16, 0));
+        let mut result = summaries(ics, &range);
+        result.sort();
+        assert_eq!(result, vec!["Instant", "Weekly"]);
+    }
+
+    #[test]
+    fn test_vtodo_time_range() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VTODO
+SUMMARY:Due
+DUE:20240105T120000Z
+END:VTODO
+BEGIN:VTODO
+SUMMARY:Undated
+END:VTODO
+BEGIN:VTODO
+SUMMARY:Created
+CREATED:20240201T000000Z
+END:VTODO
+END:VCALENDAR"#;
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        let range = ICalTimeRange::new(utc(2024, 1, 1, 0), utc(2024, 1, 6, 0));
+        let todos = vcal.comps.get_vec("VTODO").unwrap();
+        let matches: Vec<bool> = todos.iter()
+            .map(|t| t.matches_time_range("VTODO", &[], &range).unwrap())
+            .collect();
+        assert_eq!(matches, vec![true, true, false]);
+        assert_eq!(summaries(ics, &range), vec!["Undated", "Due"]);
+
+        //no date to place the undated VTODO at
+        let range = ICalTimeRange::new(None, None);
+        assert!(todos[1].matches_time_range("VTODO", &[], &range).unwrap());
+        assert_eq!(summaries(ics, &range), vec!["Due", "Created"]);
+    }
+
+    #[test]
+    fn test_vfreebusy_time_range() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VFREEBUSY
+SUMMARY:Busy
+FREEBUSY:20240102T090000Z/PT1H
+FREEBUSY:20240104T090000Z/PT1H
+END:VFREEBUSY
+END:VCALENDAR"#;
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        let range = ICalTimeRange::new(utc(2024, 1, 4, 0), utc(2024, 1, 5, 0));
+        let instances: Vec<_> = vcal.query_time_range(&range).unwrap().collect();
+        assert_eq!(instances.len(), 1);
+        assert_eq!((instances[0].start.to_string(), instances[0].end.to_string()), ("20240102T090000Z".to_string(), "20240104T100000Z".to_string()));
+        assert!(summaries(ics, &ICalTimeRange::new(utc(2024, 1, 3, 0), utc(2024, 1, 4, 0))).is_empty());
+    }
+
+    #[test]
+    fn test_open_range_infinite_rule() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+SUMMARY:Hourly
+DTSTART:20240101T090000Z
+DURATION:PT30M
+RRULE:FREQ=HOURLY
+END:VEVENT
+END:VCALENDAR"#;
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        let vevent = vcal.comps.get_vec("VEVENT").unwrap().first().unwrap();
+        assert!(vevent.matches_time_range("VEVENT", &[], &ICalTimeRange::new(utc(2025, 6, 1, 0), None)).unwrap());
+        assert!(vevent.matches_time_range("VEVENT", &[], &ICalTimeRange::new(None, None)).unwrap());
+
+        let range = ICalTimeRange::new(utc(2025, 6, 1, 0), None);
+        let starts: Vec<String> = vcal.query_time_range(&range).unwrap()
+            .take(2)
+            .map(|i| i.start.to_string())
+            .collect();
+        assert_eq!(starts, vec!["20250601T000000Z", "20250601T010000Z"]);
+    }
+
+    #[test]
+    fn test_moved_override_in_range() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:1
+SUMMARY:Master
+DTSTART:20240101T090000Z
+DURATION:PT1H
+RRULE:FREQ=DAILY;COUNT=10
+END:VEVENT
+BEGIN:VEVENT
+UID:1
+SUMMARY:Moved
+RECURRENCE-ID:20240108T090000Z
+DTSTART:20240102T150000Z
+DURATION:PT1H
+END:VEVENT
+END:VCALENDAR"#;
+        let range = ICalTimeRange::new(utc(2024, 1, 2, 12), utc(2024, 1, 2, 18));
+        assert_eq!(summaries(ics, &range), vec!["Moved"]);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::VecDeque;
+
+use chrono::{Days, NaiveDateTime, NaiveTime, TimeDelta};
+use either::Either;
+
+use crate::{
+    component::ICalComponent,
+    generator::GetPropError,
+    parameter::Range,
+    values::{
+        date::ICalDate, datetime::ICalDateTime, duration::ICalDuration,
+        period::ICalPeriod, recur::DateOrDateTime, ICalValue,
+    },
+};
+
+/// A concrete instance of a (possibly recurring) VEVENT, VTODO or VJOURNAL
+#[derive(Clone)]
+pub struct ICalInstance<'a> {
+    /// the original start of this instance (matches RECURRENCE-ID of overrides)
+    pub recurrence_id: DateOrDateTime,
+    pub start: DateOrDateTime,
+    pub end: DateOrDateTime,
+    pub master: &'a ICalComponent,
+    /// the component replacing this instance (RECURRENCE-ID match or RANGE=THISANDFUTURE)
+    pub overridden_by: Option<&'a ICalComponent>,
+}
+
+impl<'a> ICalInstance<'a> {
+    /// the component holding this instance's properties
+    pub fn component(&self) -> &'a ICalComponent {
+        self.overridden_by.unwrap_or(self.master)
+    }
+}
+
+impl ICalComponent {
+    /// Iterates the instances of this master component (RFC 5545 3.8.5) ordered by recurrence id
+    /// Merges DTSTART, RRULE & RDATE, removes EXDATE, and applies the given overrides
+    /// (sibling components with the same UID and a RECURRENCE-ID)
+    /// NOTE: overrides can move instances, so instances are not necessarily ordered by start
+    pub fn instances<'a>(&'a self, overrides: &[&'a ICalComponent]) -> Result<Instances<'a>, GetPropError> {
+        let dtstart = self.get_start()?;
+        let duration = self.get_span(&dtstart)?;
+
+        let rule: Box<dyn Iterator<Item = DateOrDateTime> + 'a> = match self.get_rrule_value() {
+            Ok(rrule) => match &dtstart {
+                DateOrDateTime::Date(d) => Box::new(rrule.date_occurrences(d).map(DateOrDateTime::Date)),
+                DateOrDateTime::DateTime(dt) => Box::new(rrule.occurrences(dt).map(DateOrDateTime::DateTime)),
+            },
+            Err(GetPropError::PropertyMissing) => Box::new(std::iter::once(dtstart.clone())),
+            Err(e) => return Err(e),
+        };
+
+        let mut rdates = self.get_date_list("RDATE")?;
+        rdates.sort_by_key(|(start, _)| start.sort_key());
+
+        let exdates = self.get_date_list("EXDATE")?
+            .into_iter()
+            .map(|(exdate, _)| exdate)
+            .collect();
+
+        let overrides = overrides.iter()
+            .filter_map(|comp| {
+                let rid = comp.get_recurrence_id_value().ok()?;
+                let this_and_future = comp.props.get("RECURRENCE-ID")
+                    .is_some_and(|p| p.params.range() == Some(Range::ThisAndFuture));
+                Some(Override { rid: rid.into(), this_and_future, comp })
+            })
+            .collect();
+
+        Ok(Instances {
+            master: self,
+            rule: rule.peekable(),
+            rdates: rdates.into(),
+            exdates,
+            overrides,
+            duration,
+            last: None,
+            future: None,
+        })
+    }
+
+    /// returns overrides (components with a RECURRENCE-ID) for the given UID
+    pub fn get_overrides(&self, comp_name: &str, uid: &str) -> Vec<&ICalComponent> {
+        self.comps.get_vec(comp_name)
+            .map(|comps| comps.iter()
+                .filter(|c| c.props.contains_key("RECURRENCE-ID"))
+                .filter(|c| c.get_uid_value().is_ok_and(|u| u == uid))
+                .collect())
+            .unwrap_or_default()
+    }
+
+    /// DTSTART as DATE or DATE-TIME
+    fn get_start(&self) -> Result<DateOrDateTime, GetPropError> {
+        Ok(self.get_dtstart_value()?.into())
+    }
+
+    /// the span of the component from DTEND/DUE (exact, or days for DATE values) or DURATION
+    /// Defaults to 1 day for DATE values and 0 for DATE-TIME values (RFC 5545 3.6.1)
+    /// NOTE: spans too long for a DURATION are WrongValueType
+    fn get_span(&self, start: &DateOrDateTime) -> Result<ICalDuration, GetPropError> {
+        let end = match self.get_dtend_value() {
+            Err(GetPropError::PropertyMissing) => self.get_due_value(),
+            end => end,
+        };
+        match end {
+            Ok(end) => return match (DateOrDateTime::from(end), start) {
+                (DateOrDateTime::Date(end), DateOrDateTime::Date(start)) => ICalDuration::days((end - *start).num_days()),
+                (end, start) => end.signed_duration_since(start).try_into(),
+            }.map_err(|_| GetPropError::WrongValueType),
+            Err(GetPropError::PropertyMissing) => {},
+            Err(e) => return Err(e),
+        }
+        match self.get_duration_value() {
+            Ok(duration) => Ok(*duration),
+            Err(GetPropError::PropertyMissing) => Ok(match start {
+                DateOrDateTime::Date(_) => ICalDuration { days: Some(1), ..Default::default() },
+                DateOrDateTime::DateTime(_) => ICalDuration::default(),
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// all values of RDATE or EXDATE properties, with the end of PERIOD values
+    /// NOTE: single values are accepted too (ex. RDATE;VALUE=DATE:19970101)
+    fn get_date_list(&self, name: &str) -> Result<Vec<(DateOrDateTime, Option<DateOrDateTime>)>, GetPropError> {
+        let mut dates = Vec::new();
+        let period = |p: &ICalPeriod| (DateOrDateTime::DateTime(p.start.clone()), Some(DateOrDateTime::DateTime(p.calc_end())));
+        for prop in self.props.get_vec(name).into_iter().flatten() {
+            match &prop.value {
+                ICalValue::DateTime(dt) => dates.push((DateOrDateTime::DateTime(dt.clone()), None)),
+                ICalValue::DateTimeList(dts) => dates.extend(dts.iter()
+                    .map(|dt| (DateOrDateTime::DateTime(dt.clone()), None))),
+                ICalValue::Date(d) => dates.push((DateOrDateTime::Date(*d), None)),
+                ICalValue::DateList(ds) => dates.extend(ds.iter()
+                    .map(|d| (DateOrDateTime::Date(*d), None))),
+                ICalValue::Period(p) => dates.push(period(p)),
+                ICalValue::PeriodList(ps) => dates.extend(ps.iter().map(period)),
+                _ => return Err(GetPropError::WrongValueType),
+            }
+        }
+        Ok(dates)
+    }
+}
+
+struct Override<'a> {
+    rid: DateOrDateTime,
+    this_and_future: bool,
+    comp: &'a ICalComponent,
+}
+
+/// An active RANGE=THISANDFUTURE override
+struct Future<'a> {
+    offset: TimeDelta,
+    duration: ICalDuration,
+    comp: &'a ICalComponent,
+}
+
+/// Instances of a component, see [ICalComponent::instances]
+pub struct Instances<'a> {
+    master: &'a ICalComponent,
+    rule: std::iter::Peekable<Box<dyn Iterator<Item = DateOrDateTime> + 'a>>,
+    rdates: VecDeque<(DateOrDateTime, Option<DateOrDateTime>)>,
+    exdates: Vec<DateOrDateTime>,
+    overrides: Vec<Override<'a>>,
+    duration: ICalDuration,
+    last: Option<NaiveDateTime>,
+    future: Option<Future<'a>>,
+}
+
+impl<'a> Instances<'a> {
+    /// next recurrence id (and explicit end for PERIOD RDATEs) from the RRULE or RDATE
+    fn next_candidate(&mut self) -> Option<(DateOrDateTime, Option<DateOrDateTime>)> {
+        let take_rdate = match (self.rule.peek(), self.rdates.front()) {
+            (None, None) => return None,
+            (Some(_), None) => false,
+            (None, Some(_)) => true,
+            (Some(rule), Some((rdate, _))) => rdate.sort_key() < rule.sort_key(),
+        };
+        match take_rdate {
+            true => self.rdates.pop_front(),
+            false => self.rule.next().map(|rid| (rid, None)),
+        }
+    }
+
+    fn make_instance(&mut self, rid: DateOrDateTime, end: Option<DateOrDateTime>) -> ICalInstance<'a> {
+        let master = self.master;
+        if let Some(ov) = self.overrides.iter().find(|o| o.rid.same_instant(&rid)) {
+            let comp = ov.comp;
+            let start = comp.get_start().unwrap_or_else(|_| rid.clone());
+            let duration = comp.get_span(&start).unwrap_or(self.duration);
+            if ov.this_and_future {
+                let offset = start.signed_duration_since(&rid);
+                self.future = Some(Future { offset, duration, comp });
+            }
+            return ICalInstance {
+                end: start.add_duration(&duration),
+                start,
+                recurrence_id: rid,
+                master,
+                overridden_by: Some(comp),
+            }
+        }
+        if let Some(future) = &self.future {
+            let start = rid.add(future.offset);
+            return ICalInstance {
+                end: start.add_duration(&future.duration),
+                start,
+                recurrence_id: rid,
+                master,
+                overridden_by: Some(future.comp),
+            }
+        }
+        ICalInstance {
+            end: end.unwrap_or_else(|| rid.add_duration(&self.duration)),
+            start: rid.clone(),
+            recurrence_id: rid,
+            master,
+            overridden_by: None,
+        }
+    }
+}
+
+impl<'a> Iterator for Instances<'a> {
+    type Item = ICalInstance<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (rid, end) = self.next_candidate()?;
+            let key = rid.sort_key();
+            if self.last == Some(key) {
+                continue
+            }
+            self.last = Some(key);
+            if self.exdates.iter().any(|ex| ex.same_instant(&rid)) {
+                continue
+            }
+            return Some(self.make_instance(rid, end))
+        }
+    }
+}
+
+impl DateOrDateTime {
+    /// key for ordering, zoned values are compared in UTC
+    /// NOTE: floating (local) times are treated as if they were UTC
+    pub(crate) fn sort_key(&self) -> NaiveDateTime {
+        match self {
+            Self::Date(d) => d.and_time(NaiveTime::MIN),
+            Self::DateTime(ICalDateTime::Local(dt)) => *dt,
+            Self::DateTime(ICalDateTime::Zoned(dt)) => dt.naive_utc(),
+        }
+    }
+
+    /// the wall clock time (DATE values are midnight)
+    pub(crate) fn naive_local(&self) -> NaiveDateTime {
+        match self {
+            Self::Date(d) => d.and_time(NaiveTime::MIN),
+            Self::DateTime(dt) => dt.naive_local(),
+        }
+    }
+
+    /// whether both refer to the same instance
+    /// DATE values match any DATE-TIME on the same day
+    pub(crate) fn same_instant(&self, other: &DateOrDateTime) -> bool {
+        match (self, other) {
+            (Self::DateTime(ICalDateTime::Zoned(a)), Self::DateTime(ICalDateTime::Zoned(b))) => a == b,
+            (Self::DateTime(a), Self::DateTime(b)) => a.naive_local() == b.naive_local(),
+            (a, b) => a.naive_local().date() == b.naive_local().date(),
+        }
+    }
+
+    /// exact duration from other to self
+    pub(crate) fn signed_duration_since(&self, other: &DateOrDateTime) -> TimeDelta {
+        match (self, other) {
+            (Self::DateTime(ICalDateTime::Zoned(a)), Self::DateTime(ICalDateTime::Zoned(b))) => a.clone() - b.clone(),
+            (a, b) => a.naive_local() - b.naive_local(),
+        }
+    }
+
+    /// DATE values are moved by whole days
+    pub(crate) fn add(&self, delta: TimeDelta) -> DateOrDateTime {
+        match self {
+            Self::Date(d) => Self::Date(add_days(d, delta.num_days())),
+            Self::DateTime(ICalDateTime::Local(dt)) => Self::DateTime(ICalDateTime::Local(*dt + delta)),
+            Self::DateTime(ICalDateTime::Zoned(dt)) => Self::DateTime(ICalDateTime::Zoned(dt.clone() + delta)),
+        }
+    }
+
+    /// RFC 5545 3.3.6: days are nominal, DATE values are moved by whole days
+    pub(crate) fn add_duration(&self, duration: &ICalDuration) -> DateOrDateTime {
+        match self {
+            Self::Date(d) => Self::Date(duration.add_to_date(d)),
+            Self::DateTime(dt) => Self::DateTime(duration.add_to(dt)),
+        }
+    }
+}
+
+fn add_days(date: &ICalDate, days: i64) -> ICalDate {
+    match days >= 0 {
+        true => *date + Days::new(days as u64),
+        false => *date - Days::new(days.unsigned_abs()),
+    }
+}
+
+impl From<Either<&ICalDateTime, &ICalDate>> for DateOrDateTime {
+    fn from(value: Either<&ICalDateTime, &ICalDate>) -> Self {
+        match value {
+            Either::Left(dt) => Self::DateTime(dt.clone()),
+            Either::Right(d) => Self::Date(*d),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+    use chrono_tz::Tz;
+
+    use crate::{component::{ICalComponent, VEVENT}, values::recur::DateOrDateTime};
+
+    fn zoned(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateOrDateTime {
+        let dt = Tz::America__New_York.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap();
+        DateOrDateTime::DateTime(dt.into())
+    }
+
+    const ICS: &str = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:1
+DTSTART;TZID=America/New_York:20240101T090000
+DTEND;TZID=America/New_York:20240101T100000
+RRULE:FREQ=DAILY;COUNT=5
+EXDATE;TZID=America/New_York:20240103T090000
+RDATE;TZID=America/New_York:20240110T120000
+END:VEVENT
+BEGIN:VEVENT
+UID:1
+RECURRENCE-ID;TZID=America/New_York:20240102T090000
+DTSTART;TZID=America/New_York:20240102T140000
+DTEND;TZID=America/New_York:20240102T160000
+SUMMARY:Moved
+END:VEVENT
+END:VCALENDAR"#;
+
+    #[test]
+    fn test_instances() {
+        let vcal = ICalComponent::from_ics(ICS).unwrap();
+        let master = vcal.comps.get_vec(VEVENT).unwrap().iter()
+            .find(|c| !c.props.contains_key("RECURRENCE-ID"))
+            .unwrap();
+        let overrides = vcal.get_overrides(VEVENT, "1");
+        let instances: Vec<_> = master.instances(&overrides).unwrap().collect();
+
+        let starts: Vec<_> = instances.iter().map(|i| i.start.clone()).collect();
+        assert_eq!(starts, vec![
+            zoned(2024, 1, 1, 9, 0),
+            zoned(2024, 1, 2, 14, 0),
+            zoned(2024, 1, 4, 9, 0),
+            zoned(2024, 1, 5, 9, 0),
+            zoned(2024, 1, 10, 12, 0),
+        ]);
+        assert_eq!(instances[1].end, zoned(2024, 1, 2, 16, 0));
+        assert_eq!(instances[1].recurrence_id, zoned(2024, 1, 2, 9, 0));
+        assert!(instances[1].overridden_by.is_some());
+        assert_eq!(instances[4].end, zoned(2024, 1, 10, 13, 0));
+    }
+
+    #[test]
+    fn test_instances_this_and_future() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:2
+DTSTART;VALUE=DATE:20240101
+RRULE:FREQ=WEEKLY;COUNT=4
+END:VEVENT
+BEGIN:VEVENT
+UID:2
+RECURRENCE-ID;RANGE=THISANDFUTURE;VALUE=DATE:20240115
+DTSTART;VALUE=DATE:20240116
+DTEND;VALUE=DATE:20240118
+END:VEVENT
+END:VCALENDAR"#;
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        let master = vcal.comps.get_vec(VEVENT).unwrap().iter()
+            .find(|c| !c.props.contains_key("RECURRENCE-ID"))
+            .unwrap();
+        let overrides = vcal.get_overrides(VEVENT, "2");
+        let instances: Vec<_> = master.instances(&overrides).unwrap().collect();
+
+        let date = |d| DateOrDateTime::Date(NaiveDate::from_ymd_opt(2024, 1, d).unwrap());
+        let spans: Vec<_> = instances.iter().map(|i| (i.start.clone(), i.end.clone())).collect();
+        assert_eq!(spans, vec![
+            (date(1), date(2)),
+            (date(8), date(9)),
+            (date(16), date(18)),
+            (date(23), date(25)),
+        ]);
+        assert!(instances[3].overridden_by.is_some());
+    }
+
+    #[test]
+    fn test_instances_nominal_duration() {
+        let ics = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+UID:3
+DTSTART;TZID=America/New_York:20240309T120000
+DURATION:P1D
+RRULE:FREQ=DAILY;COUNT=2
+END:VEVENT
+END:VCALENDAR"#;
+        let mut vcal = ICalComponent::from_ics(ics).unwrap();
+        let instances: Vec<_> = vcal.expect_vevent().instances(&[]).unwrap()
+            .map(|i| i.end)
+            .collect();
+        //the day DST starts is only 23 hours long
+        assert_eq!(instances, vec![zoned(2024, 3, 10, 12, 0), zoned(2024, 3, 11, 12, 0)]);
+    }
+}
@@ -1,100 +1,307 @@
-use crate::{component::ICalComponent, property::ICalProperty};
+use std::{borrow::Cow, fmt, io};
+
+use crate::{component::{ICalComponent, VCALENDAR, VTIMEZONE}, property::ICalProperty, values::ICalValue};
 use unicode_segmentation::UnicodeSegmentation;
 
 pub const CRLF: &str = "\r\n";
 
+/// RFC 5545 3.1: the longest line SHOULD be 75 octets
+pub const DEFAULT_LINE_WIDTH: usize = 75;
+/// the longest character (4 octets) after the folding space
+pub const MIN_LINE_WIDTH: usize = 5;
+
+/// Options for [ICalComponent::to_ics_with] and [ICalComponent::write_ics_with]
+#[derive(Clone, Debug)]
+pub struct ICalSerializeOptions {
+    /// adds a VTIMEZONE for every IANA zone used that doesn't have one
+    pub insert_vtimezones: bool,
+    /// most octets per physical line (excluding CRLF), 0 disables folding
+    /// NOTE: widths under MIN_LINE_WIDTH are raised to it
+    pub line_width: usize,
+    /// the same data always gives the same output (ex. for hashing and diffing):
+    /// names are uppercased, properties, parameters & components are sorted,
+    /// DTSTAMP etc. are written in UTC and original text from keep_raw is ignored
+    pub canonical: bool,
+}
+
+impl Default for ICalSerializeOptions {
+    fn default() -> Self {
+        Self {
+            insert_vtimezones: false,
+            line_width: DEFAULT_LINE_WIDTH,
+            canonical: false,
+        }
+    }
+}
+
 impl ICalComponent {
     pub fn to_ics(&self) -> String {
+        self.to_ics_with(&ICalSerializeOptions::default())
+    }
+
+    pub fn to_ics_with(&self, options: &ICalSerializeOptions) -> String {
         let mut ics = String::new();
-        self._to_ics(&mut ics, "VCALENDAR", true);
+        self.write_ics_fmt_with(&mut ics, options).expect("writing to a String can't fail");
         ics
     }
 
-    fn _to_ics(&self, ics: &mut String, comp_name: &str, init: bool) {
+    /// Write directly to a file, HTTP body, etc.
+    /// NOTE: output is written in small pieces, so wrap unbuffered writers in a BufWriter
+    pub fn write_ics<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        self.write_ics_with(writer, &ICalSerializeOptions::default())
+    }
+
+    pub fn write_ics_with<W: io::Write>(&self, writer: W, options: &ICalSerializeOptions) -> io::Result<()> {
+        let mut writer = IoWriter { inner: writer, error: None };
+        self.write_ics_fmt_with(&mut writer, options)
+            .map_err(|_| writer.error.unwrap_or(io::Error::other("formatter error")))
+    }
+
+    pub fn write_ics_fmt<W: fmt::Write>(&self, writer: &mut W) -> fmt::Result {
+        self.write_ics_fmt_with(writer, &ICalSerializeOptions::default())
+    }
+
+    pub fn write_ics_fmt_with<W: fmt::Write>(&self, writer: &mut W, options: &ICalSerializeOptions) -> fmt::Result {
+        let vtimezones = match options.insert_vtimezones {
+            true => self.missing_vtimezones(),
+            false => Vec::new(),
+        };
+        let name = match (self.name.is_empty(), options.canonical) {
+            (true, _) => VCALENDAR.to_string(),
+            (false, true) => self.name.to_uppercase(),
+            (false, false) => self.name.clone(),
+        };
+        self._to_ics(writer, &mut String::new(), options, &name, true, &vtimezones)?;
+        //RFC 5545 3.4: the object ends with a CRLF too
+        writer.write_str(CRLF)
+    }
+
+    /// line is a buffer reused for every content line
+    fn _to_ics<W: fmt::Write>(
+        &self,
+        ics: &mut W,
+        line: &mut String,
+        options: &ICalSerializeOptions,
+        comp_name: &str,
+        init: bool,
+        vtimezones: &[ICalComponent],
+    ) -> fmt::Result {
         if !init {
-            ics.push_str(CRLF);
+            ics.write_str(CRLF)?;
         }
-        ics.push_str("BEGIN:");
-        ics.push_str(comp_name);
-        for (prop_name, props) in &self.props {
-            for prop in props {
-                prop.to_ics(ics, prop_name);
-            }
+        ics.write_str("BEGIN:")?;
+        ics.write_str(comp_name)?;
+        if options.canonical {
+            self.write_canonical_body(ics, options, vtimezones)?;
+            ics.write_str(CRLF)?;
+            ics.write_str("END:")?;
+            return ics.write_str(comp_name)
         }
-        for (comp_name, comps) in &self.comps {
-            for comp in comps {
-                comp._to_ics(ics, comp_name, false);
-            }
+        for (prop_name, prop) in &self.props {
+            prop.to_ics(ics, line, prop_name, options.line_width)?;
+        }
+        for raw_line in &self.raw_lines {
+            fold_push(ics, raw_line, options.line_width)?;
+        }
+        //RFC 5545 3.6.5: VTIMEZONEs come before the components using them
+        for vtimezone in vtimezones {
+            vtimezone._to_ics(ics, line, options, VTIMEZONE, false, &[])?;
         }
-        ics.push_str(CRLF);
-        ics.push_str("END:");
-        ics.push_str(comp_name);
+        for (comp_name, comp) in &self.comps {
+            comp._to_ics(ics, line, options, comp_name, false, &[])?;
+        }
+        ics.write_str(CRLF)?;
+        ics.write_str("END:")?;
+        ics.write_str(comp_name)
+    }
+}
+
+impl ICalComponent {
+    /// properties sorted by name then line, and components
+    /// (VTIMEZONEs first, as they're used by the rest) by name then content
+    fn write_canonical_body<W: fmt::Write>(
+        &self,
+        ics: &mut W,
+        options: &ICalSerializeOptions,
+        vtimezones: &[ICalComponent],
+    ) -> fmt::Result {
+        let mut lines: Vec<(String, String)> = self.props.iter()
+            .map(|(name, prop)| {
+                let name = name.to_uppercase();
+                let mut line = String::new();
+                prop.push_line(&mut line, &name, true);
+                (name, line)
+            })
+            .collect();
+        lines.sort();
+        for (_, line) in &lines {
+            fold_push(ics, line, options.line_width)?;
+        }
+        for raw_line in &self.raw_lines {
+            fold_push(ics, raw_line, options.line_width)?;
+        }
+
+        let children = vtimezones.iter().map(|vtimezone| (VTIMEZONE, vtimezone))
+            .chain(self.comps.iter());
+        let mut comps = Vec::new();
+        for (name, comp) in children {
+            let name = name.to_uppercase();
+            let mut text = String::new();
+            comp._to_ics(&mut text, &mut String::new(), options, &name, false, &[])?;
+            comps.push((name != VTIMEZONE, name, text));
+        }
+        comps.sort();
+        for (_, _, text) in &comps {
+            ics.write_str(text)?;
+        }
+        Ok(())
+    }
+}
+
+/// adapts an io::Write for fmt::Write, keeping the io::Error
+struct IoWriter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> fmt::Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
     }
 }
 
 impl ICalProperty {
-    fn to_ics(&self, ics: &mut String, name: &str) {
-        let line = self.make_line(name);
-        self.fold_push(ics, &line);
-    }
-
-    /// RFC5545 3.1: Lines of text SHOULD NOT be longer than 75 octets,
-    /// excluding the line break. Long content lines SHOULD be split into a
-    /// multiple line representations using a line "folding" technique. That
-    /// is, a long line can be split between any two characters by inserting
-    /// a CRLF immediately followed by a single linear white-space character
-    fn fold_push(&self, ics: &mut String, line: &str) {
-        let graphemes = line.graphemes(true); //properly handle unicode
-        let end = graphemes.clone().count() - 1;
-        let (mut start, mut cur_size) = (0, 0);
-        for (i, g) in graphemes.enumerate() {
-            let num_bytes = g.len();
-            let at_start = start == 0;
-            let at_end = i == end;
-            let max_bytes = if at_start { 75 } else { 74 }; //account for space
-            if at_end || cur_size + num_bytes > max_bytes {
-                ics.push_str(CRLF);
-                if !at_start {
-                    ics.push(' ');
-                }
-                ics.push_str(if at_end {
-                    &line[start..]
-                } else {
-                    &line[start..i]
-                });
-                (start, cur_size) = (i, 0);
-            }
-            cur_size += num_bytes;
+    fn to_ics<W: fmt::Write>(&self, ics: &mut W, line: &mut String, name: &str, width: usize) -> fmt::Result {
+        line.clear();
+        self.push_line(line, name, false);
+        //unmodified since parsing, so the original is written
+        if let Some(raw) = self.raw.as_ref().filter(|raw| raw.matches(line)) {
+            ics.write_str(CRLF)?;
+            return ics.write_str(&raw.text)
         }
+        fold_push(ics, line, width)
     }
 
     /// RFC 5545 3.1: "name *(";" param ) ":" value CRLF"
-    fn make_line(&self, name: &str) -> String {
-        let mut line = name.to_string();
-        for (name, value) in &self.params {
-            line.push(';');
-            line.push_str(name);
-            line.push('=');
-            if value.contains(':') || value.contains(';') || value.contains(',') {
-                line.push('"');
-                line.push_str(value);
-                line.push('"');
-            }
-            else {
-                line.push_str(value);
-            }
+    /// canonical lines have sorted parameters and UTC_PROPERTIES in UTC
+    pub(crate) fn push_line(&self, line: &mut String, name: &str, canonical: bool) {
+        let value = match &self.value {
+            ICalValue::DateTime(dt) if canonical && UTC_PROPERTIES.contains(&name) => {
+                Cow::Owned(ICalValue::DateTime(dt.to_utc()))
+            },
+            value => Cow::Borrowed(value),
+        };
+        line.push_str(name);
+        //TZID always follows the time zone of DATE-TIME values
+        let has_date_times = !value.date_times().is_empty();
+        let tzid = value.tzid().map(|tzid| vec![tzid.to_string()]);
+        let mut params: Vec<(&str, &[String])> = self.params.iter()
+            .filter(|param| !(has_date_times && param.name == "TZID"))
+            .map(|param| (param.name.as_str(), param.values.as_slice()))
+            .chain(tzid.as_ref().map(|tzid| ("TZID", tzid.as_slice())))
+            .collect();
+        if canonical {
+            params.sort_by_key(|(name, _)| *name);
+        }
+        for (name, values) in params {
+            push_param(line, name, values);
         }
         line.push(':');
-        line.push_str(&self.value.serialize());
-        line
+        line.push_str(&value.serialize());
+    }
+}
+
+/// RFC5545 3.1: Lines of text SHOULD NOT be longer than 75 octets,
+/// excluding the line break. Long content lines SHOULD be split into a
+/// multiple line representations using a line "folding" technique. That
+/// is, a long line can be split between any two characters by inserting
+/// a CRLF immediately followed by a single linear white-space character
+/// NOTE: graphemes (ex. emoji or combining marks) are only split if they don't fit on a line,
+/// and then between characters (never inside a UTF-8 sequence)
+fn fold_push<W: fmt::Write>(ics: &mut W, line: &str, width: usize) -> fmt::Result {
+    ics.write_str(CRLF)?;
+    if width == 0 {
+        return ics.write_str(line)
+    }
+    let width = width.max(MIN_LINE_WIDTH);
+    //octets for the current physical line, continuation lines start with a space
+    let (mut start, mut max) = (0, width);
+    for (i, grapheme) in line.grapheme_indices(true) {
+        if i + grapheme.len() - start <= max {
+            continue
+        }
+        if i > start {
+            fold_at(ics, &line[start..i])?;
+            (start, max) = (i, width - 1);
+        }
+        //too long for any line
+        for (j, c) in grapheme.char_indices().map(|(j, c)| (i + j, c)) {
+            if j + c.len_utf8() - start > max {
+                fold_at(ics, &line[start..j])?;
+                (start, max) = (j, width - 1);
+            }
+        }
     }
+    ics.write_str(&line[start..])
+}
+
+fn fold_at<W: fmt::Write>(ics: &mut W, segment: &str) -> fmt::Result {
+    ics.write_str(segment)?;
+    ics.write_str(CRLF)?;
+    ics.write_char(' ')
+}
+
+/// RFC 5545 3.8.7.1-3, 3.8.2.1: DATE-TIMEs that MUST be in UTC
+const UTC_PROPERTIES: [&str; 4] = ["DTSTAMP", "CREATED", "LAST-MODIFIED", "COMPLETED"];
+
+/// RFC 5545 3.2: values are comma separated, and quoted if they contain ":" ";" or ","
+fn push_param(line: &mut String, name: &str, values: &[impl AsRef<str>]) {
+    line.push(';');
+    line.push_str(name);
+    line.push('=');
+    for (i, value) in values.iter().enumerate() {
+        let value = &encode_param_value(value.as_ref());
+        if i > 0 {
+            line.push(',');
+        }
+        if value.contains(':') || value.contains(';') || value.contains(',') {
+            line.push('"');
+            line.push_str(value);
+            line.push('"');
+        }
+        else {
+            line.push_str(value);
+        }
+    }
+}
+
+/// RFC 6868: newlines, double quotes and carets become ^n, ^' and ^^
+fn encode_param_value(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '^' => result.push_str("^^"),
+            '"' => result.push_str("^'"),
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => result.push_str("^n"),
+            _ => result.push(c),
+        }
+    }
+    result
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+    use proptest::{collection::vec, prelude::*, sample::select};
+    use unicode_segmentation::UnicodeSegmentation;
 
-    use crate::{property::ICalProperty, values::ICalValue};
+    use crate::{component::ICalComponent, parser::ICalParseOptions, property::{ICalParameterMap, ICalProperty}, serializer::{fold_push, ICalSerializeOptions, CRLF, DEFAULT_LINE_WIDTH, MIN_LINE_WIDTH}, values::ICalValue};
 
     #[test]
     fn test_serialize_property_line_fold() {
@@ -102,10 +309,11 @@ mod tests {
         let str = "This is a really long line. ".repeat(20);
         let prop = ICalProperty {
             value: ICalValue::Text(str),
-            params: HashMap::new(),
+            params: ICalParameterMap::new(),
+            raw: None,
         };
         let mut ics = String::new();
-        prop.to_ics(&mut ics, name);
+        prop.to_ics(&mut ics, &mut String::new(), name, DEFAULT_LINE_WIDTH).unwrap();
         for line in ics.lines() {
             let len = line.len();
             if len > 75 {
@@ -113,4 +321,151 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_serialize_tzid() {
+        let dtstart = Tz::Europe__Berlin.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap();
+        let mut prop = ICalProperty::from(dtstart);
+        prop.set_param("TZID", "Stale/Zone");
+        assert_eq!(make_line(&prop, "DTSTART"), "DTSTART;TZID=Europe/Berlin:20240701T090000");
+
+        let utc = Tz::UTC.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap();
+        let mut prop = ICalProperty::from(utc);
+        prop.set_param("TZID", "Europe/Berlin");
+        assert_eq!(make_line(&prop, "DTSTART"), "DTSTART:20240701T090000Z");
+    }
+
+    #[test]
+    fn test_serialize_insert_vtimezones() {
+        let dtstart = Tz::Europe__Berlin.with_ymd_and_hms(2024, 7, 1, 9, 0, 0).unwrap();
+        let vcal = ICalComponent::vcalendar_with_vevent(
+            ICalComponent::empty()
+                .uid("1".to_string())
+                .dtstart(dtstart.into())
+                .build()
+        );
+        assert!(!vcal.to_ics().contains("BEGIN:VTIMEZONE"));
+
+        let options = ICalSerializeOptions { insert_vtimezones: true, ..Default::default() };
+        let ics = vcal.to_ics_with(&options);
+        assert_eq!(ics.matches("BEGIN:VTIMEZONE").count(), 1);
+        assert!(ics.find("BEGIN:VTIMEZONE").unwrap() < ics.find("BEGIN:VEVENT").unwrap());
+        assert!(ics.contains("TZID:Europe/Berlin"));
+
+        //round trips through the generated VTIMEZONE
+        let mut parsed = ICalComponent::from_ics(&ics).unwrap();
+        let parsed_dtstart = parsed.expect_vevent().get_dtstart_value().unwrap().left().unwrap().clone();
+        let crate::values::datetime::ICalDateTime::Zoned(parsed_dtstart) = parsed_dtstart else {
+            panic!("DTSTART not zoned");
+        };
+        assert_eq!(parsed_dtstart.naive_utc(), dtstart.naive_utc());
+        assert_eq!(parsed.missing_vtimezones().len(), 0);
+    }
+
+    #[test]
+    fn test_write_ics() {
+        let vcal = ICalComponent::vcalendar_with_vtodo(
+            ICalComponent::empty()
+                .uid("1".to_string())
+                .description("A description long enough to be folded onto a second line ".repeat(3))
+                .build()
+        );
+        let mut bytes = Vec::new();
+        vcal.write_ics(&mut bytes).unwrap();
+        assert_eq!(String::from_utf8(bytes).unwrap(), vcal.to_ics());
+
+        let mut fmt = String::from("prefix:");
+        vcal.write_ics_fmt(&mut fmt).unwrap();
+        assert_eq!(fmt, format!("prefix:{}", vcal.to_ics()));
+
+        //io errors are returned as is
+        let err = vcal.write_ics(&mut [0u8; 16][..]).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn test_serialize_caret_encoding() {
+        let mut prop = ICalProperty::from("Meeting");
+        prop.set_param("CN", "George \"The Boss\" ^Smith")
+            .set_param("X-ADDRESS", "1 Main St\nSpringfield");
+        assert_eq!(
+            make_line(&prop, "SUMMARY"),
+            "SUMMARY;CN=George ^'The Boss^' ^^Smith;X-ADDRESS=1 Main St^nSpringfield:Meeting"
+        );
+    }
+
+    #[test]
+    fn test_serialize_canonical() {
+        let a = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//A//EN\r\nBEGIN:VEVENT\r\nUID:2\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nUID:1\r\nEND:VTODO\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTAMP;TZID=Europe/Berlin:20240701T110000\r\nATTENDEE;ROLE=CHAIR;CN=\"Jane\":mailto:jane@example.com\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let b = "BEGIN:VCALENDAR\r\nprodid:-//A//EN\r\nBEGIN:VEVENT\r\nattendee;cn=Jane;role=CHAIR:mailto:jane@example.com\r\nDTSTAMP:20240701T090000Z\r\nUID:1\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nUID:2\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nUID:1\r\nEND:VTODO\r\nVERSION:2.0\r\nEND:VCALENDAR\r\n";
+        let options = ICalSerializeOptions { canonical: true, ..Default::default() };
+        let keep_raw = ICalParseOptions { keep_raw: true, ..Default::default() };
+        let a = ICalComponent::from_ics_with(a, &keep_raw).unwrap().component.to_ics_with(&options);
+        let b = ICalComponent::from_ics(b).unwrap().to_ics_with(&options);
+        assert_eq!(a, b);
+        assert_eq!(a, "BEGIN:VCALENDAR\r\nPRODID:-//A//EN\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nATTENDEE;CN=Jane;ROLE=CHAIR:mailto:jane@example.com\r\nDTSTAMP:20240701T090000Z\r\nUID:1\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nUID:2\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nUID:1\r\nEND:VTODO\r\nEND:VCALENDAR\r\n");
+    }
+
+    #[test]
+    fn test_fold_widths() {
+        let mut folded = String::new();
+        fold_push(&mut folded, "", DEFAULT_LINE_WIDTH).unwrap();
+        assert_eq!(folded, "\r\n");
+
+        let line = "SUMMARY:".to_string() + &"中".repeat(30);
+        let mut folded = String::new();
+        fold_push(&mut folded, &line, 0).unwrap();
+        assert_eq!(folded, format!("\r\n{line}"));
+
+        let mut folded = String::new();
+        fold_push(&mut folded, "SUMMARY:中文", 1).unwrap();
+        assert_eq!(folded, "\r\nSUMMA\r\n RY:\r\n 中\r\n 文");
+    }
+
+    /// pieces that are multibyte, combine or are wider than short lines
+    const PIECES: &[&str] = &[
+        "a", " ", ";", "é", "e\u{301}", "中", "文", "😀", "🇩🇪", "👩\u{200D}👩\u{200D}👧\u{200D}👦",
+        "a\u{301}\u{302}\u{303}\u{304}\u{305}\u{306}\u{307}\u{308}\u{309}\u{30A}\u{30B}\u{30C}\u{30D}\u{30E}",
+    ];
+
+    proptest! {
+        #[test]
+        fn test_fold_properties(pieces in vec(select(PIECES), 0..120), width in MIN_LINE_WIDTH..100usize) {
+            let line = pieces.concat();
+            let physical = assert_folded(&line, width)?;
+
+            //only graphemes too wide for a line are split
+            let graphemes: Vec<(usize, &str)> = line.grapheme_indices(true).collect();
+            let mut offset = 0;
+            for (i, part) in physical[..physical.len() - 1].iter().enumerate() {
+                offset += if i == 0 { part.len() } else { part.len() - 1 };
+                let (start, grapheme) = graphemes.iter().rev().find(|(start, _)| *start <= offset).unwrap();
+                prop_assert!(*start == offset || grapheme.len() > width - 1, "split {:?} at {}", grapheme, offset);
+            }
+        }
+
+        #[test]
+        fn test_fold_any_text(line in "\\PC*", width in MIN_LINE_WIDTH..100usize) {
+            assert_folded(&line, width)?;
+        }
+    }
+
+    /// checks each physical line fits and unfolding gives back line
+    fn assert_folded(line: &str, width: usize) -> Result<Vec<String>, TestCaseError> {
+        let mut folded = String::new();
+        fold_push(&mut folded, line, width).unwrap();
+        let physical: Vec<String> = folded[CRLF.len()..].split(CRLF).map(str::to_string).collect();
+        for (i, part) in physical.iter().enumerate() {
+            prop_assert!(part.len() <= width, "{} octets > {}: {:?}", part.len(), width, part);
+            prop_assert!(i == 0 || part.starts_with(' '));
+        }
+        prop_assert_eq!(folded[CRLF.len()..].replace("\r\n ", ""), line);
+        Ok(physical)
+    }
+
+    fn make_line(prop: &ICalProperty, name: &str) -> String {
+        let mut line = String::new();
+        prop.push_line(&mut line, name, false);
+        line
+    }
 }
@@ -3,14 +3,14 @@ mod tests {
     use chrono::{NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
     use chrono_tz::Tz;
 
-    use crate::{component::ICalComponent, values::datetime::ICalDateTime};
+    use crate::{component::ICalComponent, parameter::{ParticipationRole, ParticipationStatus}, parser::ICalParseOptions, values::datetime::ICalDateTime};
 
     #[test]
     fn test_convert_x_prop() {
         let in_ics = r#"BEGIN:VCALENDAR
 X-EXAMPLE:19921217T123456
 END:VCALENDAR"#;
-        let mut vcal = ICalComponent::from_ics(&in_ics).unwrap();
+        let mut vcal = ICalComponent::from_ics(in_ics).unwrap();
 
         let x_example = vcal.get_prop("X-EXAMPLE").unwrap()
             .convert_value::<ICalDateTime>().unwrap()
@@ -31,7 +31,7 @@ SUMMARY:Old Summary
 END:VTODO
 END:VCALENDAR"#;
 
-        let mut vcal = ICalComponent::from_ics(&in_ics).unwrap();
+        let mut vcal = ICalComponent::from_ics(in_ics).unwrap();
         let vtodo = vcal.expect_vtodo();
         vtodo.summary("New Summary".to_string());
         let out_ics = vcal.to_ics();
@@ -57,7 +57,7 @@ CALSCALE:GREGORIAN
 PRODID:-//Liam Snow//ical-rs//EN
 BEGIN:VTODO
 UID:128397129837129837
-DTSTAMP:19921217T123456
+DTSTAMP;TZID=America/New_York:19921217T123456
 PERCENT-COMPLETE:10
 END:VTODO
 END:VCALENDAR"#;
@@ -88,10 +88,108 @@ X-APPLE-SORT-ORDER:740793996
 END:VTODO
 END:VCALENDAR"#;
 
-        let vcal = ICalComponent::from_ics(&in_ics).unwrap();
+        let vcal = ICalComponent::from_ics(in_ics).unwrap();
         assert_lines_match(&vcal.to_ics(), in_ics);
     }
 
+    #[test]
+    fn test_ordered_round_trip() {
+        let in_ics = "BEGIN:VCALENDAR\r\nPRODID:-//Example//EN\r\nVERSION:2.0\r\nBEGIN:VTODO\r\nUID:1\r\nEND:VTODO\r\nBEGIN:VEVENT\r\nUID:2\r\nSUMMARY:First\r\nCATEGORIES:A\r\nSUMMARY:Second\r\nEND:VEVENT\r\nBEGIN:VTODO\r\nUID:3\r\nEND:VTODO\r\nEND:VCALENDAR\r\n";
+        let vcal = ICalComponent::from_ics(in_ics).unwrap();
+        assert_eq!(vcal.name, "VCALENDAR");
+        assert_eq!(vcal.to_ics(), in_ics);
+
+        let (_, vevent) = ICalComponent::from_ics_component("BEGIN:VEVENT\r\nUID:1\r\nEND:VEVENT\r\n").unwrap();
+        assert_eq!(vevent.to_ics(), "BEGIN:VEVENT\r\nUID:1\r\nEND:VEVENT\r\n");
+    }
+
+    #[test]
+    fn test_raw_round_trip() {
+        let in_ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nuid:1\r\nDTSTART;tzid=\"Europe/Berlin\":20240101T090000\r\nDESCRIPTION:Folded at an unusual\r\n  place\r\nSUMMARY:Old\r\nX-NUM:007\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let options = ICalParseOptions { keep_raw: true, ..Default::default() };
+        let mut vcal = ICalComponent::from_ics_with(in_ics, &options).unwrap().component;
+        assert_eq!(vcal.to_ics(), in_ics);
+
+        //only the modified property is rewritten
+        vcal.expect_vevent().summary("New".to_string());
+        assert_eq!(vcal.to_ics(), in_ics.replace("SUMMARY:Old", "SUMMARY:New"));
+
+        //without keep_raw values are normalized
+        let vcal = ICalComponent::from_ics(in_ics).unwrap();
+        assert!(vcal.to_ics().contains("\r\nUID:1\r\nDTSTART;TZID=Europe/Berlin:20240101T090000\r\n"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let in_ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VTIMEZONE\r\nTZID:Custom\r\nBEGIN:STANDARD\r\nDTSTART:19701025T030000\r\nRRULE:FREQ=YEARLY;BYDAY=-1SU;BYMONTH=10\r\nTZOFFSETFROM:+0200\r\nTZOFFSETTO:+0100\r\nEND:STANDARD\r\nBEGIN:DAYLIGHT\r\nDTSTART:19700329T020000\r\nRRULE:FREQ=YEARLY;BYDAY=-1SU;BYMONTH=3\r\nTZOFFSETFROM:+0100\r\nTZOFFSETTO:+0200\r\nEND:DAYLIGHT\r\nEND:VTIMEZONE\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTAMP:20240101T120000Z\r\nDTSTART;TZID=Custom:20240701T090000\r\nDTEND;TZID=Europe/Berlin:20240701T100000\r\nRDATE;VALUE=PERIOD:20240801T090000/PT1H\r\nRRULE:FREQ=WEEKLY;COUNT=3;BYDAY=MO,-1FR\r\nATTENDEE;ROLE=CHAIR:mailto:jane@example.com\r\nSUMMARY:Serde\\, JSON\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let vcal = ICalComponent::from_ics(in_ics).unwrap();
+        let json = serde_json::to_string(&vcal).unwrap();
+        assert!(json.contains(r#"["DTSTART",{"value":{"DateTime":"2024-07-01T09:00:00+02:00[Custom]"},"params":[{"name":"TZID","values":["Custom"]}]}]"#), "{json}");
+        assert!(json.contains(r#"{"DateTime":"2024-01-01T12:00:00Z"}"#), "{json}");
+        assert!(json.contains(r#"{"UtcOffset":"+0100"}"#), "{json}");
+
+        let mut out: ICalComponent = serde_json::from_str(&json).unwrap();
+        assert_eq!(out.to_ics(), in_ics);
+        //resolved against the VTIMEZONE again, not just its offset in July
+        let dtstart = out.expect_vevent().get_dtstart_value().unwrap().left().unwrap().clone();
+        let ICalDateTime::Zoned(dtstart) = dtstart else { panic!("DTSTART should be zoned") };
+        let winter = NaiveDate::from_ymd_opt(2024, 12, 1).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        assert_eq!(dtstart.timezone().from_local_datetime(&winter).unwrap().offset().to_string(), "+01:00");
+        assert_eq!(serde_json::to_string(&out).unwrap(), json);
+    }
+
+    #[test]
+    fn test_escaped_text() {
+        let in_ics = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+SUMMARY:Lunch\, then review\; maybe
+DESCRIPTION:Agenda:\n1. C:\\Temp
+CATEGORIES:Work\,Team,Food
+END:VEVENT
+END:VCALENDAR"#;
+
+        let mut vcal = ICalComponent::from_ics(in_ics).unwrap();
+        let vevent = vcal.expect_vevent();
+        assert_eq!(vevent.get_summary_value().unwrap(), "Lunch, then review; maybe");
+        assert_eq!(vevent.get_description_value().unwrap(), "Agenda:\n1. C:\\Temp");
+        assert_eq!(vevent.get_categories_values().unwrap()[0], &vec!["Work,Team", "Food"]);
+        assert_lines_match(&vcal.to_ics(), in_ics);
+    }
+
+    #[test]
+    fn test_multi_valued_params() {
+        let attendee = "ATTENDEE;ROLE=REQ-PARTICIPANT;PARTSTAT=ACCEPTED;MEMBER=\"mailto:a@example.com\",\"mailto:b@example.com\";X-CUSTOM=1:mailto:jane@example.com";
+        let in_ics = format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\n{attendee}\nEND:VEVENT\nEND:VCALENDAR");
+
+        let mut vcal = ICalComponent::from_ics(&in_ics).unwrap();
+        let vevent = vcal.expect_vevent();
+        let params = &vevent.get_attendee_prop().unwrap()[0].params;
+        assert_eq!(params.role(), Some(ParticipationRole::ReqParticipant));
+        assert_eq!(params.partstat(), Some(ParticipationStatus::Accepted));
+        let members: Vec<_> = params.member().iter().map(|m| m.email().unwrap().to_string()).collect();
+        assert_eq!(members, vec!["a@example.com", "b@example.com"]);
+        assert_eq!(params.get("X-CUSTOM"), Some("1"));
+
+        let out_ics = vcal.to_ics().replace("\r\n ", "");
+        assert!(out_ics.lines().any(|line| line == attendee), "Output does not contain {attendee}");
+    }
+
+    #[test]
+    fn test_caret_encoded_params() {
+        let location = "LOCATION;X-ADDRESS=\"1 Main St^nSpringfield, ^'Downtown^'\";CN=a^b:Office";
+        let in_ics = format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\n{location}\nEND:VEVENT\nEND:VCALENDAR");
+
+        let mut vcal = ICalComponent::from_ics(&in_ics).unwrap();
+        let vevent = vcal.expect_vevent();
+        assert_eq!(vevent.get_prop_param("LOCATION", "X-ADDRESS"), Some("1 Main St\nSpringfield, \"Downtown\""));
+        assert_eq!(vevent.get_prop_param("LOCATION", "CN"), Some("a^b"));
+
+        let out_ics = vcal.to_ics().replace("\r\n ", "");
+        let expected = location.replace("a^b", "a^^b");
+        assert!(out_ics.lines().any(|line| line == expected), "Output does not contain {expected}");
+    }
+
     /// compare ignoring line order
     fn assert_lines_match(in_ics: &str, out_ics: &str) {
         let in_lines: Vec<&str> = in_ics.lines().collect();
@@ -118,9 +216,9 @@ ORGANIZER;DIR="ldap://example.com:6666/o=ABC%20Industries,
 END:VTODO
 END:VCALENDAR"#;
 
-        let mut vcal = ICalComponent::from_ics(&in_ics).unwrap();
+        let mut vcal = ICalComponent::from_ics(in_ics).unwrap();
         let vtodo = vcal.expect_vtodo();
-        assert_eq!(vtodo.get_organizer_value().unwrap(), "mailto:jimdo@example.com");
+        assert_eq!(vtodo.get_organizer_value().unwrap().email(), Some("jimdo@example.com"));
         assert_eq!(
             vtodo.get_organizer_prop().unwrap().get_param("DIR").unwrap(),
             "ldap://example.com:6666/o=ABC%20Industries,c=US???(cn=Jim%20Dolittle)"
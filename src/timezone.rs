@@ -0,0 +1,335 @@
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{Datelike, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+
+use crate::{component::{ICalComponent, VTIMEZONE}, values::{datetime::ICalDateTime, recur::DateOrDateTime, ICalValue}};
+
+pub mod generate;
+
+pub const STANDARD: &str = "STANDARD";
+pub const DAYLIGHT: &str = "DAYLIGHT";
+
+/// transitions of recurring observances are computed up to this year
+const TRANSITIONS_UNTIL_YEAR: i32 = 2200;
+
+/// A time zone from the IANA database or a calendar's VTIMEZONE
+#[derive(Clone, Debug, PartialEq)]
+pub enum ICalTimeZone {
+    Iana(Tz),
+    Custom(Arc<VTimeZone>),
+}
+
+impl ICalTimeZone {
+    pub fn tzid(&self) -> &str {
+        match self {
+            Self::Iana(tz) => tz.name(),
+            Self::Custom(vtz) => &vtz.tzid,
+        }
+    }
+
+    pub fn is_utc(&self) -> bool {
+        matches!(self, Self::Iana(Tz::UTC) | Self::Iana(Tz::Etc__UTC))
+    }
+
+    fn offset(&self, fixed: FixedOffset) -> ICalTzOffset {
+        ICalTzOffset { tz: self.clone(), fixed }
+    }
+}
+
+impl From<Tz> for ICalTimeZone {
+    fn from(value: Tz) -> Self {
+        Self::Iana(value)
+    }
+}
+
+/// The offset of an [ICalTimeZone] at some instant
+#[derive(Clone, Debug, PartialEq)]
+pub struct ICalTzOffset {
+    tz: ICalTimeZone,
+    fixed: FixedOffset,
+}
+
+impl ICalTzOffset {
+    pub fn tz(&self) -> &ICalTimeZone {
+        &self.tz
+    }
+}
+
+impl Offset for ICalTzOffset {
+    fn fix(&self) -> FixedOffset {
+        self.fixed
+    }
+}
+
+impl Display for ICalTzOffset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.fixed)
+    }
+}
+
+impl TimeZone for ICalTimeZone {
+    type Offset = ICalTzOffset;
+
+    fn from_offset(offset: &Self::Offset) -> Self {
+        offset.tz.clone()
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<Self::Offset> {
+        self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<Self::Offset> {
+        match self {
+            Self::Iana(tz) => tz.offset_from_local_datetime(local).map(|o| self.offset(o.fix())),
+            Self::Custom(vtz) => vtz.offset_at_local(local).map(|o| self.offset(o)),
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> Self::Offset {
+        self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> Self::Offset {
+        match self {
+            Self::Iana(tz) => self.offset(tz.offset_from_utc_datetime(utc).fix()),
+            Self::Custom(vtz) => self.offset(vtz.offset_at_utc(utc)),
+        }
+    }
+}
+
+/// RFC 5545 3.6.5: a time zone defined by STANDARD & DAYLIGHT observances
+#[derive(Clone, Debug, PartialEq)]
+pub struct VTimeZone {
+    pub tzid: String,
+    /// sorted by onset
+    transitions: Vec<Transition>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Transition {
+    /// onset in UTC
+    utc: NaiveDateTime,
+    offset_from: FixedOffset,
+    offset_to: FixedOffset,
+}
+
+impl VTimeZone {
+    /// builds the time zone from a VTIMEZONE component
+    pub fn from_component(vtimezone: &ICalComponent) -> anyhow::Result<Self> {
+        let tzid = vtimezone.get_tzid_value()
+            .map_err(|_| anyhow!("VTIMEZONE missing TZID"))?
+            .to_string();
+        let mut transitions = Vec::new();
+        for name in [STANDARD, DAYLIGHT] {
+            for observance in vtimezone.comps.get_vec(name).into_iter().flatten() {
+                add_transitions(&mut transitions, observance)
+                    .with_context(|| format!("Parsing {name} of VTIMEZONE {tzid}"))?;
+            }
+        }
+        if transitions.is_empty() {
+            bail!("VTIMEZONE {tzid} has no observances");
+        }
+        transitions.sort_by_key(|t| t.utc);
+        Ok(Self { tzid, transitions })
+    }
+
+    /// a zone with a single offset, for a TZID whose VTIMEZONE isn't known yet
+    #[cfg(feature = "serde")]
+    pub(crate) fn fixed(tzid: &str, offset: FixedOffset) -> Self {
+        let transition = Transition { utc: NaiveDateTime::default(), offset_from: offset, offset_to: offset };
+        Self { tzid: tzid.to_string(), transitions: vec![transition] }
+    }
+
+    /// the offset in effect at the UTC time
+    /// NOTE: times before the first observance use its TZOFFSETFROM
+    pub fn offset_at_utc(&self, utc: &NaiveDateTime) -> FixedOffset {
+        match self.transitions.partition_point(|t| t.utc <= *utc) {
+            0 => self.transitions[0].offset_from,
+            i => self.transitions[i - 1].offset_to,
+        }
+    }
+
+    /// the offset(s) matching a local time, none for times in a gap
+    pub fn offset_at_local(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        let day = TimeDelta::days(1);
+        let mut candidates = vec![
+            self.offset_at_utc(&(*local - day)),
+            self.offset_at_utc(&(*local + day)),
+        ];
+        candidates.dedup();
+        let mut valid: Vec<FixedOffset> = candidates.into_iter()
+            .filter(|offset| self.offset_at_utc(&(*local - *offset)) == *offset)
+            .collect();
+        //earliest instant (largest offset) first
+        valid.sort_by_key(|offset| std::cmp::Reverse(offset.local_minus_utc()));
+        match valid.as_slice() {
+            [] => LocalResult::None,
+            [offset] => LocalResult::Single(*offset),
+            [earliest, latest, ..] => LocalResult::Ambiguous(*earliest, *latest),
+        }
+    }
+}
+
+/// every onset of a STANDARD or DAYLIGHT observance
+fn add_transitions(transitions: &mut Vec<Transition>, observance: &ICalComponent) -> anyhow::Result<()> {
+    let offset_from = *observance.get_tzoffsetfrom_value().map_err(|_| anyhow!("Missing TZOFFSETFROM"))?;
+    let offset_to = *observance.get_tzoffsetto_value().map_err(|_| anyhow!("Missing TZOFFSETTO"))?;
+    let dtstart = DateOrDateTime::from(observance.get_dtstart_value().map_err(|_| anyhow!("Missing DTSTART"))?)
+        .naive_local();
+
+    let mut onsets = vec![dtstart];
+    if let Ok(rrule) = observance.get_rrule_value() {
+        //UNTIL is in UTC, occurrences are in the local time before the onset
+        let until = rrule.until.as_ref().map(|until| match until {
+            DateOrDateTime::DateTime(ICalDateTime::Zoned(dt)) => dt.naive_utc() + offset_from,
+            other => other.naive_local(),
+        });
+        onsets.extend(rrule.local_occurrences(dtstart, until)
+            .skip(1)
+            .take_while(|dt| dt.year() <= TRANSITIONS_UNTIL_YEAR));
+    }
+    for prop in observance.props.get_vec("RDATE").into_iter().flatten() {
+        match &prop.value {
+            ICalValue::DateTime(dt) => onsets.push(dt.naive_local()),
+            ICalValue::DateTimeList(dts) => onsets.extend(dts.iter().map(|dt| dt.naive_local())),
+            _ => bail!("Invalid RDATE"),
+        }
+    }
+
+    transitions.extend(onsets.into_iter().map(|local| Transition {
+        utc: local - offset_from,
+        offset_from,
+        offset_to,
+    }));
+    Ok(())
+}
+
+/// Time zones for resolving TZID parameters
+#[derive(Clone, Debug, Default)]
+pub struct ICalTimeZones {
+    custom: HashMap<String, ICalTimeZone>,
+}
+
+impl ICalTimeZones {
+    /// collects the VTIMEZONEs of a VCALENDAR, skipping invalid ones
+    pub fn from_vcalendar(vcal: &ICalComponent) -> Self {
+        let mut tzs = Self::default();
+        for vtimezone in vcal.comps.get_vec(VTIMEZONE).into_iter().flatten() {
+            if let Ok(vtz) = VTimeZone::from_component(vtimezone) {
+                tzs.insert(vtz);
+            }
+        }
+        tzs
+    }
+
+    pub fn insert(&mut self, vtz: VTimeZone) -> &mut Self {
+        self.custom.insert(vtz.tzid.clone(), ICalTimeZone::Custom(Arc::new(vtz)));
+        self
+    }
+
+    /// resolves against the calendar's VTIMEZONEs first, then the IANA database
+    pub fn resolve(&self, tzid: &str) -> Option<ICalTimeZone> {
+        if let Some(tz) = self.custom.get(tzid) {
+            return Some(tz.clone())
+        }
+        Tz::from_str(tzid).ok().map(ICalTimeZone::Iana)
+    }
+}
+
+impl ICalComponent {
+    /// moves date-times to the component's VTIMEZONEs with the same TZID, keeping the instant
+    #[cfg(feature = "serde")]
+    pub(crate) fn resolve_timezones(&mut self) {
+        let tzs = ICalTimeZones::from_vcalendar(self);
+        if !tzs.custom.is_empty() {
+            self.move_to_timezones(&tzs);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    fn move_to_timezones(&mut self, tzs: &ICalTimeZones) {
+        for prop in self.props.values_mut() {
+            for dt in prop.value.date_times_mut() {
+                if let Some(tz) = dt.tzid().and_then(|tzid| tzs.custom.get(tzid)) {
+                    if let ICalDateTime::Zoned(zoned) = dt {
+                        *zoned = zoned.with_timezone(tz);
+                    }
+                }
+            }
+        }
+        for comp in self.comps.values_mut() {
+            comp.move_to_timezones(tzs);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Timelike};
+
+    use crate::{component::ICalComponent, values::datetime::ICalDateTime};
+
+    const ICS: &str = r#"BEGIN:VCALENDAR
+BEGIN:VEVENT
+DTSTART;TZID=W. Europe Standard Time:20240701T090000
+DTEND;TZID=W. Europe Standard Time:20240101T090000
+END:VEVENT
+BEGIN:VTIMEZONE
+TZID:W. Europe Standard Time
+BEGIN:STANDARD
+DTSTART:16010101T030000
+TZOFFSETFROM:+0200
+TZOFFSETTO:+0100
+RRULE:FREQ=YEARLY;INTERVAL=1;BYDAY=-1SU;BYMONTH=10
+END:STANDARD
+BEGIN:DAYLIGHT
+DTSTART:16010101T020000
+TZOFFSETFROM:+0100
+TZOFFSETTO:+0200
+RRULE:FREQ=YEARLY;INTERVAL=1;BYDAY=-1SU;BYMONTH=3
+END:DAYLIGHT
+END:VTIMEZONE
+END:VCALENDAR"#;
+
+    #[test]
+    fn test_custom_timezone() {
+        let mut vcal = ICalComponent::from_ics(ICS).unwrap();
+        let vevent = vcal.expect_vevent();
+        let ICalDateTime::Zoned(summer) = vevent.get_dtstart_value().unwrap().left().unwrap().clone() else {
+            panic!("DTSTART not zoned");
+        };
+        assert_eq!(summer.timezone().tzid(), "W. Europe Standard Time");
+        assert_eq!(summer.naive_utc().hour(), 7);
+        let ICalDateTime::Zoned(winter) = vevent.get_dtend_value().unwrap().left().unwrap().clone() else {
+            panic!("DTEND not zoned");
+        };
+        assert_eq!(winter.naive_utc().hour(), 8);
+    }
+
+    #[test]
+    fn test_custom_timezone_transitions() {
+        let vcal = ICalComponent::from_ics(ICS).unwrap();
+        let tzs = super::ICalTimeZones::from_vcalendar(&vcal);
+        let tz = tzs.resolve("W. Europe Standard Time").unwrap();
+
+        //2024-03-31 02:30 does not exist, 2024-10-27 02:30 happens twice
+        let gap = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap().and_hms_opt(2, 30, 0).unwrap();
+        assert!(tz.from_local_datetime(&gap).single().is_none());
+        let overlap = NaiveDate::from_ymd_opt(2024, 10, 27).unwrap().and_hms_opt(2, 30, 0).unwrap();
+        let (first, second) = match tz.from_local_datetime(&overlap) {
+            chrono::LocalResult::Ambiguous(a, b) => (a, b),
+            _ => panic!("Expected ambiguous time"),
+        };
+        assert_eq!(first.naive_utc().hour(), 0);
+        assert_eq!(second.naive_utc().hour(), 1);
+    }
+
+    #[test]
+    fn test_iana_fallback() {
+        let tzs = super::ICalTimeZones::default();
+        assert_eq!(tzs.resolve("America/New_York").unwrap().tzid(), "America/New_York");
+        assert!(tzs.resolve("W. Europe Standard Time").is_none());
+    }
+}
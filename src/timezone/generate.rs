@@ -0,0 +1,286 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz};
+
+use crate::{component::{ICalComponent, VTIMEZONE}, values::{datetime::ICalDateTime, recur::{ByDay, DateOrDateTime, Frequency, ICalRecur}}};
+use super::{ICalTimeZone, DAYLIGHT, STANDARD};
+
+/// An offset change of an IANA zone
+#[derive(Clone, Debug, PartialEq)]
+struct TzTransition {
+    /// onset in UTC
+    utc: NaiveDateTime,
+    offset_from: FixedOffset,
+    offset_to: FixedOffset,
+    daylight: bool,
+    name: Option<String>,
+}
+
+impl TzTransition {
+    /// RFC 5545 3.6.5: DTSTART & RDATE are in the local time before the onset
+    fn local(&self) -> NaiveDateTime {
+        self.utc + self.offset_from
+    }
+
+    fn comp_name(&self) -> &'static str {
+        if self.daylight { DAYLIGHT } else { STANDARD }
+    }
+
+    fn same_observance(&self, other: &Self) -> bool {
+        self.daylight == other.daylight && self.offset_from == other.offset_from
+            && self.offset_to == other.offset_to && self.name == other.name
+    }
+}
+
+impl ICalComponent {
+    /// RFC 5545 3.6.5: builds a VTIMEZONE for an IANA zone covering every
+    /// transition between start and end. Observances that follow a yearly
+    /// pattern up to end are written as an RRULE, so they also apply after end
+    pub fn vtimezone_from_tz(tz: Tz, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        let start = start.naive_utc().with_nanosecond(0).unwrap();
+        let end = end.naive_utc();
+        let mut vtimezone = ICalComponent::empty();
+        vtimezone.tzid(tz.name().to_string());
+
+        let transitions = transitions(tz, start, end);
+        if transitions.is_empty() {
+            //a single observance with the offset in effect for the whole range
+            let offset = tz.offset_from_utc_datetime(&start);
+            let onset = TzTransition {
+                utc: start,
+                offset_from: offset.fix(),
+                offset_to: offset.fix(),
+                daylight: !offset.dst_offset().is_zero(),
+                name: offset.abbreviation().map(str::to_string),
+            };
+            vtimezone.insert_comp(onset.comp_name(), observance(&onset, &[], None));
+            return vtimezone.build()
+        }
+
+        let mut groups: Vec<Vec<TzTransition>> = Vec::new();
+        for transition in transitions {
+            match groups.iter_mut().find(|group| group[0].same_observance(&transition)) {
+                Some(group) => group.push(transition),
+                None => groups.push(vec![transition]),
+            }
+        }
+
+        for group in groups {
+            let (split, rrule) = match yearly_rule(&group, end) {
+                Some((split, rrule)) => (split, Some(rrule)),
+                None => (group.len(), None),
+            };
+            let (dated, ruled) = group.split_at(split);
+            if let Some((first, rest)) = dated.split_first() {
+                vtimezone.insert_comp(first.comp_name(), observance(first, rest, None));
+            }
+            if let Some(first) = ruled.first() {
+                vtimezone.insert_comp(first.comp_name(), observance(first, &[], rrule));
+            }
+        }
+        vtimezone.build()
+    }
+
+    /// VTIMEZONEs for the IANA zones referenced by DATE-TIME values anywhere in
+    /// this calendar that don't already have a VTIMEZONE
+    pub fn missing_vtimezones(&self) -> Vec<ICalComponent> {
+        let mut zones = BTreeMap::new();
+        self.collect_zones(&mut zones);
+        for vtimezone in self.comps.get_vec(VTIMEZONE).into_iter().flatten() {
+            if let Ok(tzid) = vtimezone.get_tzid_value() {
+                zones.remove(tzid.as_str());
+            }
+        }
+        //pad by a year so yearly observances are recognized as rules
+        let pad = TimeDelta::days(366);
+        zones.into_values()
+            .map(|(tz, min, max)| Self::vtimezone_from_tz(
+                tz,
+                Utc.from_utc_datetime(&(min - pad)),
+                Utc.from_utc_datetime(&(max + pad)),
+            ))
+            .collect()
+    }
+
+    /// referenced non-UTC IANA zones with the earliest & latest UTC time used
+    fn collect_zones(&self, zones: &mut BTreeMap<&'static str, (Tz, NaiveDateTime, NaiveDateTime)>) {
+        for props in self.props.iter_all().map(|(_, props)| props) {
+            for dt in props.iter().flat_map(|prop| prop.value.date_times()) {
+                let ICalDateTime::Zoned(dt) = dt else { continue };
+                let ICalTimeZone::Iana(tz) = dt.offset().tz() else { continue };
+                if dt.offset().tz().is_utc() {
+                    continue
+                }
+                let utc = dt.naive_utc();
+                let range = zones.entry(tz.name()).or_insert((*tz, utc, utc));
+                range.1 = range.1.min(utc);
+                range.2 = range.2.max(utc);
+            }
+        }
+        for comps in self.comps.iter_all().map(|(_, comps)| comps) {
+            for comp in comps {
+                comp.collect_zones(zones);
+            }
+        }
+    }
+}
+
+/// every offset change of the zone after start up to end
+/// NOTE: found by checking each day, so two changes in one day are missed
+fn transitions(tz: Tz, start: NaiveDateTime, end: NaiveDateTime) -> Vec<TzTransition> {
+    let state = |utc: &NaiveDateTime| {
+        let offset = tz.offset_from_utc_datetime(utc);
+        (offset.fix(), !offset.dst_offset().is_zero())
+    };
+    let mut transitions = Vec::new();
+    let (mut cur, mut cur_state) = (start, state(&start));
+    while cur < end {
+        let next = (cur + TimeDelta::days(1)).min(end);
+        let next_state = state(&next);
+        if next_state != cur_state {
+            //narrow down to the first second of the new offset
+            let (mut before, mut after) = (cur, next);
+            while (after - before).num_seconds() > 1 {
+                let mid = before + TimeDelta::seconds((after - before).num_seconds() / 2);
+                if state(&mid) == cur_state { before = mid } else { after = mid }
+            }
+            transitions.push(TzTransition {
+                utc: after,
+                offset_from: cur_state.0,
+                offset_to: next_state.0,
+                daylight: next_state.1,
+                name: tz.offset_from_utc_datetime(&after).abbreviation().map(str::to_string),
+            });
+            cur_state = next_state;
+        }
+        cur = next;
+    }
+    transitions
+}
+
+/// finds the longest run at the end of the observance's onsets that falls on
+/// the same weekday of the same month every year, returning where the run
+/// starts and the matching RRULE (with UNTIL if it stopped before end)
+fn yearly_rule(group: &[TzTransition], end: NaiveDateTime) -> Option<(usize, ICalRecur)> {
+    let last = group.last()?;
+    let mut ordinals = weekday_ordinals(&last.local().date());
+    let mut start = group.len() - 1;
+    while start > 0 {
+        let (prev, next) = (group[start - 1].local(), group[start].local());
+        let common: Vec<i8> = weekday_ordinals(&prev.date()).into_iter()
+            .filter(|ordinal| ordinals.contains(ordinal))
+            .collect();
+        if prev.year() + 1 != next.year() || prev.month() != next.month()
+            || prev.weekday() != next.weekday() || prev.time() != next.time() || common.is_empty() {
+            break
+        }
+        ordinals = common;
+        start -= 1;
+    }
+    if start == group.len() - 1 {
+        return None
+    }
+
+    let local = last.local();
+    //another onset was due before end, so the rule was replaced
+    let until = (last.utc + TimeDelta::days(366) < end).then(|| DateOrDateTime::DateTime(
+        ICalDateTime::Zoned(ICalTimeZone::Iana(Tz::UTC).from_utc_datetime(&last.utc))
+    ));
+    Some((start, ICalRecur {
+        freq: Frequency::Yearly,
+        until,
+        bymonth: vec![local.month() as u8],
+        //prefer "last weekday" rules, they stay correct in longer months
+        byday: vec![ByDay {
+            ordwk: ordinals.iter().min().copied(),
+            weekday: local.weekday().into(),
+        }],
+        ..Default::default()
+    }))
+}
+
+/// the BYDAY ordinals matching this date in its month (ex. 2 & -1 for the 2nd & last)
+fn weekday_ordinals(date: &NaiveDate) -> Vec<i8> {
+    let mut ordinals = vec![((date.day() - 1) / 7 + 1) as i8];
+    if (*date + TimeDelta::days(7)).month() != date.month() {
+        ordinals.push(-1);
+    }
+    ordinals
+}
+
+fn observance(onset: &TzTransition, rdates: &[TzTransition], rrule: Option<ICalRecur>) -> ICalComponent {
+    let mut observance = ICalComponent::empty();
+    observance
+        .dtstart(ICalDateTime::Local(onset.local()))
+        .tzoffsetfrom(onset.offset_from)
+        .tzoffsetto(onset.offset_to);
+    if let Some(name) = &onset.name {
+        observance.tzname(name.clone());
+    }
+    if let Some(rrule) = rrule {
+        observance.rrule(rrule);
+    }
+    if !rdates.is_empty() {
+        observance.rdate(rdates.iter().map(|t| ICalDateTime::Local(t.local())).collect());
+    }
+    observance.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    use crate::{component::ICalComponent, timezone::{VTimeZone, DAYLIGHT, STANDARD}, values::ICalValueTrait};
+
+    #[test]
+    fn test_vtimezone_rules() {
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let mut vtimezone = ICalComponent::vtimezone_from_tz(Tz::America__New_York, start, end);
+        let ics = vtimezone.to_ics();
+        assert!(ics.contains("TZID:America/New_York"));
+
+        let daylight = vtimezone.get_comp(DAYLIGHT).unwrap();
+        assert_eq!(daylight.get_tzoffsetfrom_value().unwrap().serialize(), "-0500");
+        assert_eq!(daylight.get_tzoffsetto_value().unwrap().serialize(), "-0400");
+        assert_eq!(daylight.get_tzname_value().unwrap(), "EDT");
+        assert_eq!(daylight.get_rrule_value().unwrap().serialize(), "FREQ=YEARLY;BYDAY=2SU;BYMONTH=3");
+        let standard = vtimezone.get_comp(STANDARD).unwrap();
+        assert_eq!(standard.get_rrule_value().unwrap().serialize(), "FREQ=YEARLY;BYDAY=1SU;BYMONTH=11");
+    }
+
+    #[test]
+    fn test_vtimezone_matches_iana() {
+        let start = Utc.with_ymd_and_hms(1990, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap();
+        for tz in [Tz::America__New_York, Tz::Europe__London, Tz::Australia__Sydney, Tz::Asia__Kolkata] {
+            let vtimezone = ICalComponent::vtimezone_from_tz(tz, start, end);
+            let vtz = VTimeZone::from_component(&vtimezone).unwrap();
+            let mut day = NaiveDate::from_ymd_opt(1990, 1, 2).unwrap().and_hms_opt(0, 0, 0).unwrap();
+            while day.and_utc() < end + chrono::TimeDelta::days(3650) {
+                for hour in [0, 6, 12, 18] {
+                    let utc = day + chrono::TimeDelta::hours(hour);
+                    assert_eq!(
+                        vtz.offset_at_utc(&utc),
+                        chrono::Offset::fix(&tz.offset_from_utc_datetime(&utc)),
+                        "{} at {utc}", tz.name()
+                    );
+                }
+                day += chrono::TimeDelta::days(1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_vtimezone_no_transitions() {
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap();
+        let mut vtimezone = ICalComponent::vtimezone_from_tz(Tz::Asia__Tokyo, start, end);
+        let standard = vtimezone.get_comp(STANDARD).unwrap();
+        assert_eq!(standard.get_tzoffsetfrom_value().unwrap().serialize(), "+0900");
+        assert_eq!(standard.get_tzoffsetto_value().unwrap().serialize(), "+0900");
+        assert!(vtimezone.get_comp(DAYLIGHT).is_none());
+    }
+}
@@ -1,6 +1,6 @@
-use crate::property::ICalParameterMap;
+use crate::{property::ICalParameterMap, timezone::ICalTimeZones};
 use either::Either;
-use crate::values::{binary::ICalBinary, boolean::ICalBoolean, date::{ICalDate, ICalDateList}, datetime::{ICalDateTime, ICalDateTimeList}, duration::ICalDuration, float::ICalFloat, geo::ICalGeo, integer::ICalInteger, period::{ICalPeriod, ICalPeriodList}, recur::ICalRecur, text::{ICalText, ICalTextList}, time::ICalTime};
+use crate::values::{binary::ICalBinary, boolean::ICalBoolean, date::{ICalDate, ICalDateList}, datetime::{ICalDateTime, ICalDateTimeList}, duration::ICalDuration, float::ICalFloat, geo::ICalGeo, integer::ICalInteger, period::{EndOrDuration, ICalPeriod, ICalPeriodList}, recur::ICalRecur, text::{ICalText, ICalTextList}, time::ICalTime, utc_offset::ICalUtcOffset, cal_address::ICalCalAddress, uri::ICalUri, unknown::ICalUnknown};
 
 pub mod date;
 pub mod datetime;
@@ -14,9 +14,12 @@ pub mod period;
 pub mod recur;
 pub mod time;
 pub mod geo;
+pub mod utc_offset;
+pub mod cal_address;
+pub mod uri;
+pub mod unknown;
 
 gen_prop_value_enum!(
-    /// NOTE: CAL-ADDRESS, URI, and UTC-OFFSET are represented as TEXT
     Binary,
     Boolean,
     Date,
@@ -33,48 +36,70 @@ gen_prop_value_enum!(
     Text,
     TextList,
     Geo,
+    #[cfg_attr(feature = "serde", serde(with = "utc_offset::serde_utc_offset"))]
+    UtcOffset,
+    CalAddress,
+    Uri,
+    Unknown,
 );
 
 pub trait ICalValueTrait: Sized {
     fn parse(value: &str, params: &ICalParameterMap) -> anyhow::Result<Self>;
+
+    /// parse, resolving TZID parameters against the calendar's time zones
+    fn parse_in(value: &str, params: &ICalParameterMap, _tzs: &ICalTimeZones) -> anyhow::Result<Self> {
+        Self::parse(value, params)
+    }
+
     fn serialize(&self) -> String;
 }
 
 impl ICalValue {
     //TODO move to generator
-    pub(crate) fn from_default(name: &str, value: &str, params: &ICalParameterMap) -> anyhow::Result<Self> {
+    pub(crate) fn from_default(name: &str, value: &str, params: &ICalParameterMap, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         Ok(match name {
             "COMPLETED" | "CREATED" | "DTEND" | "DTSTAMP" |
             "DTSTART" | "DUE" | "LAST-MODIFIED" | "RECURRENCE-ID"
-                => Self::DateTime(ICalDateTime::parse(value, params)?),
+                => Self::DateTime(ICalDateTime::parse_in(value, params, tzs)?),
             "EXDATE" | "RDATE"
-                => Self::DateTimeList(ICalDateTimeList::parse(value, params)?),
+                => Self::DateTimeList(ICalDateTimeList::parse_in(value, params, tzs)?),
             "DURATION" | "TRIGGER"
                 => Self::Duration(ICalDuration::parse(value, params)?),
             "PERCENT-COMPLETE" | "PRIORITY" | "SEQUENCE" | "REPEAT"
                 => Self::Integer(ICalInteger::parse(value, params)?),
             "GEO" => Self::Geo(ICalGeo::parse(value, params)?),
-            "FREEBUSY" => Self::Period(ICalPeriod::parse(value, params)?),
+            "TZOFFSETFROM" | "TZOFFSETTO"
+                => Self::UtcOffset(ICalUtcOffset::parse(value, params)?),
+            "ATTENDEE" | "ORGANIZER"
+                => Self::CalAddress(ICalCalAddress::parse(value, params)?),
+            "ATTACH" | "TZURL" | "URL"
+                => Self::Uri(<ICalUri as ICalValueTrait>::parse(value, params)?),
+            "FREEBUSY" => Self::Period(ICalPeriod::parse_in(value, params, tzs)?),
             "RRULE" => Self::Recur(ICalRecur::parse(value, params)?),
             "CATEGORIES" | "RESOURCES"
                 => Self::TextList(ICalTextList::parse(value, params)?),
-            _ => Self::Text(ICalText::parse(value, params)?)
+            "ACTION" | "CALSCALE" | "CLASS" | "COMMENT" | "CONTACT" | "DESCRIPTION" |
+            "LOCATION" | "METHOD" | "PRODID" | "RELATED-TO" | "STATUS" | "SUMMARY" |
+            "TRANSP" | "TZID" | "TZNAME" | "UID" | "VERSION"
+                => Self::Text(ICalText::parse(value, params)?),
+            //X-, IANA & structured (REQUEST-STATUS) properties: escaping isn't TEXT's
+            _ => Self::Unknown(ICalUnknown::parse(value, params)?)
         })
     }
 
-    pub (crate) fn from_value_param(value_param: &str, value: &str, params: &ICalParameterMap) -> anyhow::Result<Self> {
+    pub (crate) fn from_value_param(value_param: &str, value: &str, params: &ICalParameterMap, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         Ok(match value_param {
             "DATE" => match value.contains(',') {
                 true => Self::DateList(ICalDateList::parse(value, params)?),
                 false => Self::Date(ICalDate::parse(value, params)?),
             },
             "DATE-TIME" => match value.contains(',') {
-                true => Self::DateTimeList(ICalDateTimeList::parse(value, params)?),
-                false => Self::DateTime(ICalDateTime::parse(value, params)?),
+                true => Self::DateTimeList(ICalDateTimeList::parse_in(value, params, tzs)?),
+                false => Self::DateTime(ICalDateTime::parse_in(value, params, tzs)?),
             },
             "PERIOD" => match value.contains(',') {
-                true => Self::PeriodList(ICalPeriodList::parse(value, params)?),
-                false => Self::Period(ICalPeriod::parse(value, params)?),
+                true => Self::PeriodList(ICalPeriodList::parse_in(value, params, tzs)?),
+                false => Self::Period(ICalPeriod::parse_in(value, params, tzs)?),
             },
             "INTEGER" => Self::Integer(ICalInteger::parse(value, params)?),
             "DURATION" => Self::Duration(ICalDuration::parse(value, params)?),
@@ -84,10 +109,53 @@ impl ICalValue {
             "RECUR" => Self::Recur(ICalRecur::parse(value, params)?),
             "BINARY" => Self::Binary(ICalBinary::parse(value, params)?),
             "GEO" => Self::Geo(ICalGeo::parse(value, params)?),
-            _ => Self::Text(ICalText::parse(value, params)?)
+            "UTC-OFFSET" => Self::UtcOffset(ICalUtcOffset::parse(value, params)?),
+            "CAL-ADDRESS" => Self::CalAddress(ICalCalAddress::parse(value, params)?),
+            "URI" => Self::Uri(<ICalUri as ICalValueTrait>::parse(value, params)?),
+            "TEXT" => Self::Text(ICalText::parse(value, params)?),
+            _ => Self::Unknown(ICalUnknown::parse(value, params)?)
         })
     }
 
+    /// every DATE-TIME in the value, including period starts & ends
+    pub(crate) fn date_times(&self) -> Vec<&ICalDateTime> {
+        fn period(p: &ICalPeriod) -> Vec<&ICalDateTime> {
+            match &p.end_or_duration {
+                EndOrDuration::End(end) => vec![&p.start, end],
+                EndOrDuration::Duration(_) => vec![&p.start],
+            }
+        }
+        match self {
+            Self::DateTime(dt) => vec![dt],
+            Self::DateTimeList(dts) => dts.iter().collect(),
+            Self::Period(p) => period(p),
+            Self::PeriodList(ps) => ps.iter().flat_map(period).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub(crate) fn date_times_mut(&mut self) -> Vec<&mut ICalDateTime> {
+        fn period(p: &mut ICalPeriod) -> Vec<&mut ICalDateTime> {
+            match &mut p.end_or_duration {
+                EndOrDuration::End(end) => vec![&mut p.start, end],
+                EndOrDuration::Duration(_) => vec![&mut p.start],
+            }
+        }
+        match self {
+            Self::DateTime(dt) => vec![dt],
+            Self::DateTimeList(dts) => dts.iter_mut().collect(),
+            Self::Period(p) => period(p),
+            Self::PeriodList(ps) => ps.iter_mut().flat_map(period).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// the TZID parameter for DATE-TIME values, None for local & UTC times
+    pub fn tzid(&self) -> Option<&str> {
+        self.date_times().into_iter().find_map(|dt| dt.tzid())
+    }
+
     pub fn to_value_param(&self) -> &str {
         match self {
             Self::Binary(_) => "BINARY",
@@ -106,6 +174,10 @@ impl ICalValue {
             Self::Text(_) => "TEXT",
             Self::TextList(_) => "TEXT",
             Self::Geo(_) => "GEO",
+            Self::UtcOffset(_) => "UTC-OFFSET",
+            Self::CalAddress(_) => "CAL-ADDRESS",
+            Self::Uri(_) => "URI",
+            Self::Unknown(_) => "UNKNOWN",
         }
     }
 }
@@ -119,7 +191,7 @@ pub trait GetEitherFromICalValue<'a>: Sized {
 }
 
 gen_get_either_from_ical_prop_value!(
-    Text Binary,
+    Uri Binary,
     DateTime Date,
     DateTimeList DateList,
     Duration DateTime,
@@ -127,14 +199,14 @@ gen_get_either_from_ical_prop_value!(
 
 macro_rules! gen_prop_value_enum {
     (
-        $(#[$enum_meta:meta])*
-        $($typ:ident,)+
+        $($(#[$variant_meta:meta])* $typ:ident,)+
     ) => {
         paste::paste! {
-            $(#[$enum_meta])*
             #[derive(Clone)]
+            #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
             pub enum ICalValue {
                 $(
+                    $(#[$variant_meta])*
                     $typ([<ICal $typ>]),
                 )+
             }
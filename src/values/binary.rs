@@ -30,15 +30,13 @@ impl ICalValueTrait for ICalBinary {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use crate::values::binary::*;
 
     #[test]
     fn test_binary() {
         let value = "AAABAAEAEBAQAAEABAAoAQAAFgAAACgAAAAQAAAAIAAAAAEABAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACAAAAAgIAAAICAgADAwMAA////AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAMwAAAAAAABNEMQAAAAAAAkQgAAAAAAJEREQgAAACECQ0QgEgAAQxQzM0E0AABERCRCREQAADRDJEJEQwAAAhA0QwEQAAAAAEREAAAAAAAAREQAAAAAAAAkQgAAAAAAAAMgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
-        let mut params = HashMap::new();
-        params.insert("ENCODING".to_string(), "BASE64".to_string());
+        let mut params = ICalParameterMap::new();
+        params.insert("ENCODING", "BASE64");
         let bin = ICalBinary::parse(value, &params).expect("Failed to parse!");
         // assert_eq!(bin...?);
         let s = ICalValueTrait::serialize(&bin);
@@ -15,8 +15,6 @@ impl ICalValueTrait for ICalBoolean {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use crate::values::boolean::*;
 
     #[test]
@@ -30,7 +28,7 @@ mod tests {
     }
 
     fn assert_boolean(value: &str, expected: bool) {
-        let result = ICalBoolean::parse(value, &HashMap::new()).expect("Failed to parse!");
+        let result = ICalBoolean::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
         assert_eq!(result, expected);
         let s = ICalValueTrait::serialize(&result);
         assert_eq!(s, value);
@@ -0,0 +1,80 @@
+use std::fmt::Display;
+
+use anyhow::{bail, Context};
+use url::Url;
+
+use crate::property::{ICalParameterMap, ICalProperty};
+use super::{ICalValue, ICalValueTrait};
+
+/// RFC 5545 3.3.3 Calendar User Address
+/// A URI, usually "mailto:" (ex. "mailto:jane_doe@example.com")
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ICalCalAddress(pub Url);
+
+impl ICalValueTrait for ICalCalAddress {
+    fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
+        let url = Url::parse(value).with_context(|| format!("Invalid CAL-ADDRESS {value}"))?;
+        Ok(Self(url))
+    }
+
+    fn serialize(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+impl ICalCalAddress {
+    /// a "mailto:" address for the email
+    pub fn mailto(email: &str) -> anyhow::Result<Self> {
+        if !email.contains('@') {
+            bail!("Invalid email {email}");
+        }
+        let url = Url::parse(&format!("mailto:{email}")).with_context(|| format!("Invalid email {email}"))?;
+        Ok(Self(url))
+    }
+
+    /// the email of "mailto:" addresses
+    pub fn email(&self) -> Option<&str> {
+        match self.0.scheme().eq_ignore_ascii_case("mailto") {
+            true => Some(self.0.path()),
+            false => None,
+        }
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.0
+    }
+}
+
+impl Display for ICalCalAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<ICalCalAddress> for ICalProperty {
+    fn from(value: ICalCalAddress) -> Self {
+        Self::from_value(ICalValue::CalAddress(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::cal_address::*;
+
+    #[test]
+    fn test_cal_address_mailto() {
+        let value = "mailto:jane_doe@example.com";
+        let address = ICalCalAddress::parse(value, &ICalParameterMap::new()).unwrap();
+        assert_eq!(address.email(), Some("jane_doe@example.com"));
+        assert_eq!(address.serialize(), value);
+        assert_eq!(ICalCalAddress::mailto("jane_doe@example.com").unwrap(), address);
+    }
+
+    #[test]
+    fn test_cal_address_other_scheme() {
+        let address = ICalCalAddress::parse("urn:uuid:b7ba2d30-1d4c-4ec1-9b4d-7e4e1a9e1b4c", &ICalParameterMap::new()).unwrap();
+        assert_eq!(address.email(), None);
+        assert!(ICalCalAddress::parse("jane_doe@example.com", &ICalParameterMap::new()).is_err());
+    }
+}
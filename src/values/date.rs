@@ -36,15 +36,13 @@ impl ICalValueTrait for ICalDateList {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use crate::values::date::*;
 
     #[test]
     fn test_date() {
         let value = "20140517";
         let expected = NaiveDate::from_ymd_opt(2014, 5, 17).unwrap();
-        let date = ICalDate::parse(value, &HashMap::new()).expect("Failed to parse!");
+        let date = ICalDate::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
         assert_eq!(date, expected);
         let s = ICalValueTrait::serialize(&date);
         assert_eq!(s, value);
@@ -1,10 +1,17 @@
-use std::{fmt::Display, str::FromStr};
+use std::fmt::Display;
+#[cfg(feature = "serde")]
+use std::sync::Arc;
 use anyhow::anyhow;
 
-use chrono::{DateTime, NaiveDateTime, TimeZone};
+use chrono::{DateTime, LocalResult, NaiveDateTime, Offset, TimeDelta, TimeZone};
 use chrono_tz::Tz;
 
-use crate::property::{ICalParameterMap, ICalProperty};
+#[cfg(feature = "serde")]
+use chrono::SecondsFormat;
+
+use crate::{property::{ICalParameterMap, ICalProperty}, timezone::{ICalTimeZone, ICalTimeZones}};
+#[cfg(feature = "serde")]
+use crate::timezone::VTimeZone;
 use super::{ICalValue, ICalValueTrait};
 
 /// RFC 5545 3.3.5
@@ -12,27 +19,28 @@ use super::{ICalValue, ICalValueTrait};
 pub enum ICalDateTime {
     ///FORM #1: Local Time
     Local(NaiveDateTime),
-    ///FORM #2: UTC Time, FORM #3: Time zone
-    Zoned(DateTime<Tz>),
+    ///FORM #2: UTC Time, FORM #3: Time zone (IANA or from a VTIMEZONE)
+    Zoned(DateTime<ICalTimeZone>),
 }
 
 const FORMAT: &str = "%Y%m%dT%H%M%S";
 
 impl ICalValueTrait for ICalDateTime {
     fn parse(value: &str, params: &ICalParameterMap) -> anyhow::Result<Self> {
+        Self::parse_in(value, params, &ICalTimeZones::default())
+    }
+
+    fn parse_in(value: &str, params: &ICalParameterMap, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         let is_utc = value.ends_with('Z');
         let value = if is_utc { value.trim_end_matches('Z') } else { value };
 
         let local = NaiveDateTime::parse_from_str(value, FORMAT)?;
 
-        if params.contains_key("TZID") {
-            let timezone_str = params.get("TZID").unwrap();
-            let timezone = Tz::from_str(timezone_str)?;
-            let dt = timezone.from_local_datetime(&local).single().ok_or(anyhow!("Failed to transfer into timezone"))?;
-            Ok(Self::Zoned(dt))
+        if let Some(tzid) = params.get("TZID") {
+            let timezone = tzs.resolve(tzid).ok_or(anyhow!("Unknown TZID {tzid}"))?;
+            Ok(Self::Zoned(resolve_local(&timezone, &local)))
         } else if is_utc {
-            let dt = Tz::UTC.from_local_datetime(&local).single().ok_or(anyhow!("Failed to tranfer into UTC"))?;
-            Ok(Self::Zoned(dt))
+            Ok(Self::Zoned(ICalTimeZone::Iana(Tz::UTC).from_utc_datetime(&local)))
         } else {
             Ok(Self::Local(local))
         }
@@ -44,20 +52,66 @@ impl ICalValueTrait for ICalDateTime {
                 dt.format(FORMAT).to_string()
             },
             ICalDateTime::Zoned(dt) => {
-                let suffix = if dt.timezone() == Tz::UTC { "Z" } else { "" };
+                let suffix = if dt.timezone().is_utc() { "Z" } else { "" };
                 dt.format(FORMAT).to_string() + suffix
             },
         }
     }
 }
 
+impl ICalDateTime {
+    /// the wall clock time, ignoring the timezone
+    pub fn naive_local(&self) -> NaiveDateTime {
+        match self {
+            ICalDateTime::Local(dt) => *dt,
+            ICalDateTime::Zoned(dt) => dt.naive_local(),
+        }
+    }
+
+    /// the same instant in UTC, local times are unchanged
+    pub fn to_utc(&self) -> Self {
+        match self {
+            ICalDateTime::Local(dt) => ICalDateTime::Local(*dt),
+            ICalDateTime::Zoned(dt) => ICalDateTime::Zoned(dt.with_timezone(&ICalTimeZone::Iana(Tz::UTC))),
+        }
+    }
+
+    /// the TZID parameter for this value, None for local & UTC times
+    pub fn tzid(&self) -> Option<&str> {
+        match self {
+            ICalDateTime::Zoned(dt) if !dt.timezone().is_utc() => Some(dt.offset().tz().tzid()),
+            _ => None,
+        }
+    }
+}
+
+/// RFC 5545 3.3.5: resolves a local time in a timezone
+/// Ambiguous times use the first occurrence, and times in a gap
+/// are interpreted using the UTC offset before the gap
+pub(crate) fn resolve_local<T: TimeZone>(tz: &T, local: &NaiveDateTime) -> DateTime<T> {
+    match tz.from_local_datetime(local) {
+        LocalResult::Single(dt) => dt,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => {
+            let before = *local - TimeDelta::hours(12);
+            let offset = tz.offset_from_local_datetime(&before).earliest()
+                .unwrap_or_else(|| tz.offset_from_utc_datetime(&before));
+            tz.from_utc_datetime(&(*local - offset.fix()))
+        },
+    }
+}
+
 //TODO test
 pub type ICalDateTimeList = Vec<ICalDateTime>;
 
 impl ICalValueTrait for ICalDateTimeList {
     fn parse(values: &str, params: &ICalParameterMap) -> anyhow::Result<Self> {
+        Self::parse_in(values, params, &ICalTimeZones::default())
+    }
+
+    fn parse_in(values: &str, params: &ICalParameterMap, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         values.split(',').try_fold(Vec::new(), |mut acc, value| {
-            acc.push(ICalDateTime::parse(value, params)?);
+            acc.push(ICalDateTime::parse_in(value, params, tzs)?);
             Ok(acc)
         })
     }
@@ -67,10 +121,94 @@ impl ICalValueTrait for ICalDateTimeList {
     }
 }
 
+impl From<NaiveDateTime> for ICalProperty {
+    fn from(value: NaiveDateTime) -> Self {
+        Self::from_value(ICalValue::DateTime(value.into()))
+    }
+}
+
+impl From<DateTime<Tz>> for ICalProperty {
+    fn from(value: DateTime<Tz>) -> Self {
+        Self::from_value(ICalValue::DateTime(value.into()))
+    }
+}
+
+impl From<DateTime<ICalTimeZone>> for ICalProperty {
+    fn from(value: DateTime<ICalTimeZone>) -> Self {
+        Self::from_value(ICalValue::DateTime(value.into()))
+    }
+}
+
+impl From<NaiveDateTime> for ICalDateTime {
+    fn from(value: NaiveDateTime) -> Self {
+        Self::Local(value)
+    }
+}
+
+impl From<DateTime<Tz>> for ICalDateTime {
+    fn from(value: DateTime<Tz>) -> Self {
+        Self::Zoned(value.with_timezone(&value.timezone().into()))
+    }
+}
+
+impl From<DateTime<ICalTimeZone>> for ICalDateTime {
+    fn from(value: DateTime<ICalTimeZone>) -> Self {
+        Self::Zoned(value)
+    }
+}
+
+impl Display for ICalDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self {
+            ICalDateTime::Local(dt) => write!(f, "{}", dt),
+            ICalDateTime::Zoned(dt) => write!(f, "{}", dt),
+        }
+    }
+}
+
+/// RFC 3339 with the TZID in brackets (RFC 9557), ex. "2024-07-01T09:00:00+02:00[Europe/Berlin]",
+/// "2024-07-01T07:00:00Z" for UTC and "2024-07-01T09:00:00" for local times
+#[cfg(feature = "serde")]
+impl serde::Serialize for ICalDateTime {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = match self {
+            Self::Local(dt) => dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string(),
+            Self::Zoned(dt) if dt.timezone().is_utc() => dt.to_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            Self::Zoned(dt) => format!("{}[{}]", dt.fixed_offset().to_rfc3339(), dt.timezone().tzid()),
+        };
+        serializer.serialize_str(&value)
+    }
+}
+
+/// NOTE: TZIDs that aren't IANA names keep their offset at that instant,
+/// until deserializing the VCALENDAR resolves them against its VTIMEZONEs
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ICalDateTime {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+        Self::from_rfc9557(&value).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ICalDateTime {
+    fn from_rfc9557(value: &str) -> anyhow::Result<Self> {
+        let Some((value, tzid)) = value.strip_suffix(']').and_then(|value| value.split_once('[')) else {
+            return match DateTime::parse_from_rfc3339(value) {
+                Ok(dt) => Ok(Self::Zoned(dt.with_timezone(&ICalTimeZone::Iana(Tz::UTC)))),
+                Err(_) => Ok(Self::Local(value.parse()?)),
+            }
+        };
+        let dt = DateTime::parse_from_rfc3339(value)?;
+        let timezone = ICalTimeZones::default().resolve(tzid)
+            .unwrap_or_else(|| ICalTimeZone::Custom(Arc::new(VTimeZone::fixed(tzid, *dt.offset()))));
+        Ok(Self::Zoned(dt.with_timezone(&timezone)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use chrono::NaiveDate;
     use chrono::NaiveTime;
 
@@ -83,62 +221,29 @@ mod tests {
             NaiveDate::from_ymd_opt(2014, 5, 17).unwrap(),
             NaiveTime::from_hms_opt(12, 34, 56).unwrap()
         );
-        assert_datetime(value, &HashMap::new(), ICalDateTime::Local(expected));
+        assert_datetime(value, &ICalParameterMap::new(), ICalDateTime::Local(expected));
     }
 
     #[test]
     fn test_datetime_utc() {
         let value = "20140517T123456Z";
         let expected = Tz::UTC.with_ymd_and_hms(2014, 5, 17, 12, 34, 56).unwrap();
-        assert_datetime(value, &HashMap::new(), ICalDateTime::Zoned(expected));
+        assert_datetime(value, &ICalParameterMap::new(), expected.into());
     }
 
     #[test]
     fn test_datetime_tz() {
         let value = "19921217T123456";
-        let mut params = HashMap::new();
-        params.insert("TZID".to_string(), "America/New_York".to_string());
+        let mut params = ICalParameterMap::new();
+        params.insert("TZID", "America/New_York");
         let expected = Tz::America__New_York.with_ymd_and_hms(1992, 12, 17, 12, 34, 56).unwrap();
-        assert_datetime(value, &params, ICalDateTime::Zoned(expected));
+        assert_datetime(value, &params, expected.into());
     }
 
-    fn assert_datetime(value: &str, params: &HashMap<String, String>, expected: ICalDateTime) {
-        let result = ICalDateTime::parse(value, &params).expect("Failed to parse!");
+    fn assert_datetime(value: &str, params: &ICalParameterMap, expected: ICalDateTime) {
+        let result = ICalDateTime::parse(value, params).expect("Failed to parse!");
         assert_eq!(result, expected);
         let s = ICalValueTrait::serialize(&result);
         assert_eq!(s, value);
     }
 }
-
-impl From<NaiveDateTime> for ICalProperty {
-    fn from(value: NaiveDateTime) -> Self {
-        Self::from_value(ICalValue::DateTime(value.into()))
-    }
-}
-
-impl From<DateTime<Tz>> for ICalProperty {
-    fn from(value: DateTime<Tz>) -> Self {
-        Self::from_value(ICalValue::DateTime(value.into()))
-    }
-}
-
-impl From<NaiveDateTime> for ICalDateTime {
-    fn from(value: NaiveDateTime) -> Self {
-        Self::Local(value)
-    }
-}
-
-impl From<DateTime<Tz>> for ICalDateTime {
-    fn from(value: DateTime<Tz>) -> Self {
-        Self::Zoned(value)
-    }
-}
-
-impl Display for ICalDateTime {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            ICalDateTime::Local(dt) => write!(f, "{}", dt),
-            ICalDateTime::Zoned(dt) => write!(f, "{}", dt),
-        }
-    }
-}
@@ -1,9 +1,11 @@
-use anyhow::{anyhow, bail};
+use anyhow::{anyhow, bail, Context};
+
+use std::ops::Neg;
 
 use chrono::TimeDelta;
 
 use crate::property::ICalParameterMap;
-use super::ICalValueTrait;
+use super::{date::ICalDate, datetime::{resolve_local, ICalDateTime}, ICalValueTrait};
 
 /// RFC 5545 3.3.6 Duration
 /// Syntax: ["+" / "-"] "P" (date / time / week)
@@ -14,89 +16,163 @@ use super::ICalValueTrait;
 ///  "P15DT5H0M20S" = 15 days, 5 hours, 20 seconds
 ///  "P7W" = 7 weeks
 ///  "-P1D" = Negative 1 day
-pub type ICalDuration = TimeDelta;
+/// Weeks and days are nominal (a day is 23 hours when DST starts),
+/// hours, minutes and seconds are exact
+/// Parts are kept as written (ex. "PT90M" or "P0D"), None when missing
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ICalDuration {
+    pub negative: bool,
+    pub weeks: Option<u32>,
+    pub days: Option<u32>,
+    pub hours: Option<u32>,
+    pub minutes: Option<u32>,
+    pub seconds: Option<u32>,
+}
 
 impl ICalValueTrait for ICalDuration {
     /// The RFC is strict on either being date (day + time), time, or week
     /// but this system is more relaxed
     /// This also does not require time to include a T
     fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
-        // println!("parsing duration {}", value);
-        let (inverted, rest) = pop_sign(value);
+        let (negative, rest) = pop_sign(value);
         let duration = parse_duration(rest)?;
-        Ok(if inverted { -duration } else { duration })
+        Ok(Self { negative, ..duration })
     }
 
     fn serialize(&self) -> String {
-        let is_neg = self.le(&TimeDelta::zero());
-        let prefix = if is_neg { "-P" } else { "P" };
-        let dur_abs = self.abs();
-        let mut str = prefix.to_string();
-
-        let weeks = dur_abs.num_weeks();
-        let total_days = dur_abs.num_days();
-        let days = total_days - (weeks * 7);
-        let hours = dur_abs.num_hours() - (total_days * 24);
-        let minutes = dur_abs.num_minutes() - (dur_abs.num_hours() * 60);
-        let seconds = dur_abs.num_seconds() - (dur_abs.num_minutes() * 60);
-        let has_time = hours > 0 || minutes > 0 || seconds > 0;
-
-        //dur-weeks
-        if !has_time && days == 0 { //has exact number of weeks
-            push_comp(&mut str, weeks, 'W');
+        let mut str = if self.negative { "-P" } else { "P" }.to_string();
+        push_comp(&mut str, self.weeks, 'W');
+        push_comp(&mut str, self.days, 'D');
+        if self.hours.is_some() || self.minutes.is_some() || self.seconds.is_some() {
+            str.push('T');
+            push_comp(&mut str, self.hours, 'H');
+            push_comp(&mut str, self.minutes, 'M');
+            push_comp(&mut str, self.seconds, 'S');
         }
-        else {
-            if total_days > 0 {
-                push_comp(&mut str, total_days, 'D');
-            }
-            if has_time {
-                serialize_time(&mut str, hours, minutes, seconds);
-            }
+        if str.ends_with('P') {
+            return "PT0S".to_string()
         }
-
         str
     }
 }
 
-fn serialize_time(str: &mut String, hours: i64, minutes: i64, seconds: i64) {
-    str.push('T');
-    if seconds > 0 {
-        push_comp(str, hours, 'H');
-        push_comp(str, minutes, 'M');
-        push_comp(str, seconds, 'S');
+impl ICalDuration {
+    pub fn weeks(weeks: i64) -> anyhow::Result<Self> {
+        Ok(Self { negative: weeks.is_negative(), weeks: Some(to_u32(weeks.unsigned_abs())?), ..Default::default() })
+    }
+
+    pub fn days(days: i64) -> anyhow::Result<Self> {
+        Ok(Self { negative: days.is_negative(), days: Some(to_u32(days.unsigned_abs())?), ..Default::default() })
+    }
+
+    /// an exact duration, written in hours, minutes and seconds (ex. "PT1H30M")
+    pub fn seconds(seconds: i64) -> anyhow::Result<Self> {
+        let abs = seconds.unsigned_abs();
+        let part = |value: u64| -> anyhow::Result<Option<u32>> {
+            Ok(if value > 0 { Some(to_u32(value)?) } else { None })
+        };
+        Ok(Self {
+            negative: seconds.is_negative(),
+            weeks: None,
+            days: None,
+            hours: part(abs / 3600)?,
+            minutes: part(abs / 60 % 60)?,
+            seconds: if abs == 0 { Some(0) } else { part(abs % 60)? },
+        })
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.nominal_days() == 0 && self.exact().is_zero()
+    }
+
+    /// the signed number of nominal days (weeks & days)
+    pub fn nominal_days(&self) -> i64 {
+        self.signed(part(self.weeks) * 7 + part(self.days))
+    }
+
+    /// the signed exact part (hours, minutes & seconds)
+    pub fn exact(&self) -> TimeDelta {
+        TimeDelta::seconds(self.signed(part(self.hours) * 3600 + part(self.minutes) * 60 + part(self.seconds)))
+    }
+
+    /// approximates the duration, treating every day as 24 hours
+    pub fn to_time_delta(&self) -> TimeDelta {
+        TimeDelta::days(self.nominal_days()) + self.exact()
+    }
+
+    /// RFC 5545 3.3.6: days are added in local time (keeping the time of day
+    /// across DST changes), then the exact part is added
+    pub fn add_to(&self, dt: &ICalDateTime) -> ICalDateTime {
+        let days = TimeDelta::days(self.nominal_days());
+        match dt {
+            ICalDateTime::Local(dt) => ICalDateTime::Local(*dt + days + self.exact()),
+            ICalDateTime::Zoned(dt) => {
+                let local = resolve_local(&dt.timezone(), &(dt.naive_local() + days));
+                ICalDateTime::Zoned(local + self.exact())
+            },
+        }
+    }
+
+    /// adds the nominal days and whole days of the exact part
+    pub fn add_to_date(&self, date: &ICalDate) -> ICalDate {
+        *date + TimeDelta::days(self.nominal_days() + self.exact().num_days())
+    }
+
+    fn signed(&self, value: i64) -> i64 {
+        if self.negative { -value } else { value }
     }
-    else if minutes > 0 {
-        push_comp(str, hours, 'H');
-        push_comp(str, minutes, 'M');
+}
+
+impl Neg for ICalDuration {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self { negative: !self.negative, ..self }
     }
-    else if hours > 0 {
-        push_comp(str, hours, 'H');
+}
+
+/// an exact duration
+impl TryFrom<TimeDelta> for ICalDuration {
+    type Error = anyhow::Error;
+
+    fn try_from(value: TimeDelta) -> anyhow::Result<Self> {
+        Self::seconds(value.num_seconds())
     }
 }
 
-fn push_comp(str: &mut String, num: i64, typ: char) {
-    str.push_str(num.to_string().as_str());
-    str.push(typ);
+fn to_u32(value: u64) -> anyhow::Result<u32> {
+    u32::try_from(value).with_context(|| format!("Duration part {value} too large"))
+}
+
+fn part(value: Option<u32>) -> i64 {
+    value.unwrap_or(0) as i64
 }
 
-fn parse_duration(v: &str) -> anyhow::Result<TimeDelta> {
+fn push_comp(str: &mut String, num: Option<u32>, typ: char) {
+    if let Some(num) = num {
+        str.push_str(num.to_string().as_str());
+        str.push(typ);
+    }
+}
+
+fn parse_duration(v: &str) -> anyhow::Result<ICalDuration> {
     let mut parts = v.split_inclusive(char::is_uppercase);
     let first_part = parts.next().ok_or(anyhow!("Duration is empty"))?;
     if first_part != "P" {
         bail!("Duration string missing P start char")
     }
-    let comps = parts.into_iter()
+    let comps = parts
         .filter(|&p| p != "T")
-        .try_fold([0; 5], |mut acc, part| -> anyhow::Result<[i64; 5]> {
+        .try_fold([None; 5], |mut acc, part| -> anyhow::Result<[Option<u32>; 5]> {
             let (num, label) = part.split_at(part.len() - 1);
             let idx = "WDHMS".find(label)
                 .ok_or(anyhow!("Unexpected label {} in duration string", label))?;
-            acc[idx] = num.parse()?;
+            acc[idx] = Some(num.parse().with_context(|| format!("Invalid duration {v}"))?);
             Ok(acc)
         })?;
-    Ok(TimeDelta::weeks(comps[0]) + TimeDelta::days(comps[1]) +
-       TimeDelta::hours(comps[2]) + TimeDelta::minutes(comps[3]) +
-       TimeDelta::seconds(comps[4]))
+    let [weeks, days, hours, minutes, seconds] = comps;
+    Ok(ICalDuration { negative: false, weeks, days, hours, minutes, seconds })
 }
 
 fn pop_sign(s: &str) -> (bool, &str) {
@@ -111,40 +187,70 @@ fn pop_sign(s: &str) -> (bool, &str) {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
 
     use crate::values::duration::*;
 
     #[test]
     fn test_duration_date() {
-        assert_duration("P15DT5H0M20S", 2, 1, 5, 0, 20);
+        assert_duration("P15DT5H0M20S", false, [None, Some(15), Some(5), Some(0), Some(20)]);
     }
 
     #[test]
     fn test_duration_weeks() {
-        assert_duration("P7W", 7, 0, 0, 0, 0);
+        assert_duration("P7W", false, [Some(7), None, None, None, None]);
     }
 
     #[test]
     fn test_duration_negative() {
-        assert_duration("-P1D", 0, -1, 0, 0, 0);
+        assert_duration("-P1D", true, [None, Some(1), None, None, None]);
     }
 
-    fn assert_duration(value: &str, expected_weeks: i64, expected_days: i64, expected_hours: i64, expected_minutes: i64, expected_seconds: i64) {
-        let dur = ICalDuration::parse(value, &HashMap::new()).expect("Failed to parse!");
+    #[test]
+    fn test_duration_as_written() {
+        assert_duration("PT90M", false, [None, None, None, Some(90), None]);
+        assert_duration("P0D", false, [None, Some(0), None, None, None]);
+        assert_eq!(ICalDuration::default().serialize(), "PT0S");
+        assert_eq!(ICalDuration::seconds(5400).unwrap().serialize(), "PT1H30M");
+        assert_eq!(ICalDuration::seconds(0).unwrap().serialize(), "PT0S");
+    }
+
+    #[test]
+    fn test_duration_too_large() {
+        assert!(ICalDuration::days(i64::from(u32::MAX) + 1).is_err());
+        assert!(ICalDuration::weeks(i64::MIN).is_err());
+        assert!(ICalDuration::try_from(TimeDelta::hours(i64::from(u32::MAX) + 1)).is_err());
+        assert_eq!(ICalDuration::days(-(u32::MAX as i64)).unwrap().nominal_days(), -(u32::MAX as i64));
+        assert!(ICalDuration::parse("P4294967296D", &ICalParameterMap::new()).is_err());
+    }
 
-        let weeks = dur.num_weeks();
-        let days = dur.num_days() - (weeks * 7);
-        let hours = dur.num_hours() - (dur.num_days() * 24);
-        let minutes = dur.num_minutes() - (dur.num_hours() * 60);
-        let seconds = dur.num_seconds() - (dur.num_minutes() * 60);
+    #[test]
+    fn test_duration_nominal_vs_exact() {
+        assert_duration("PT24H", false, [None, None, Some(24), None, None]);
+        assert_duration("PT0S", false, [None, None, None, None, Some(0)]);
+        assert_ne!(
+            ICalDuration::parse("P1D", &ICalParameterMap::new()).unwrap(),
+            ICalDuration::parse("PT24H", &ICalParameterMap::new()).unwrap()
+        );
+    }
 
-        assert_eq!(weeks, expected_weeks, "Weeks wrong");
-        assert_eq!(days, expected_days, "Days wrong");
-        assert_eq!(hours, expected_hours, "Hours wrong");
-        assert_eq!(minutes, expected_minutes, "Minutes wrong");
-        assert_eq!(seconds, expected_seconds, "Seconds wrong");
+    #[test]
+    fn test_duration_add_across_dst() {
+        //DST starts 2024-03-10 02:00 in New York
+        let tz = Tz::America__New_York;
+        let start: ICalDateTime = tz.with_ymd_and_hms(2024, 3, 9, 12, 0, 0).unwrap().into();
+        let one_day = ICalDuration::days(1).unwrap().add_to(&start);
+        assert_eq!(one_day, tz.with_ymd_and_hms(2024, 3, 10, 12, 0, 0).unwrap().into());
+        let exact_day = ICalDuration::seconds(24 * 3600).unwrap().add_to(&start);
+        assert_eq!(exact_day, tz.with_ymd_and_hms(2024, 3, 10, 13, 0, 0).unwrap().into());
+        let back = (-ICalDuration::days(1).unwrap()).add_to(&one_day);
+        assert_eq!(back, start);
+    }
 
+    fn assert_duration(value: &str, negative: bool, [weeks, days, hours, minutes, seconds]: [Option<u32>; 5]) {
+        let dur = ICalDuration::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
+        assert_eq!(dur, ICalDuration { negative, weeks, days, hours, minutes, seconds });
         let s = ICalValueTrait::serialize(&dur);
         assert_eq!(s, value, "Serialization wrong");
     }
@@ -6,7 +6,7 @@ pub type ICalFloat = f64;
 
 impl ICalValueTrait for ICalFloat {
     fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
-        Ok(value.parse().context("Parsing ICalFloat")?)
+        value.parse().context("Parsing ICalFloat")
     }
 
     fn serialize(&self) -> String {
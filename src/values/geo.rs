@@ -1,6 +1,6 @@
 use crate::property::ICalParameterMap;
 use super::ICalValueTrait;
-use anyhow::{anyhow, Context, bail};
+use anyhow::{Context, bail};
 
 pub type ICalGeo = (f64, f64);
 
@@ -6,7 +6,7 @@ pub type ICalInteger = i32;
 
 impl ICalValueTrait for ICalInteger {
     fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
-        Ok(value.parse().context("Parsing ICalInteger")?)
+        value.parse().context("Parsing ICalInteger")
     }
 
     fn serialize(&self) -> String {
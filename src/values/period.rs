@@ -1,15 +1,17 @@
-use crate::property::ICalParameterMap;
+use crate::{property::ICalParameterMap, timezone::ICalTimeZones};
 use super::ICalValueTrait;
 use super::{datetime::ICalDateTime, duration::ICalDuration};
 use anyhow::{anyhow, Context};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ICalPeriod {
     pub start: ICalDateTime,
     pub end_or_duration: EndOrDuration,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EndOrDuration {
     End(ICalDateTime),
     Duration(ICalDuration)
@@ -17,11 +19,15 @@ pub enum EndOrDuration {
 
 impl ICalValueTrait for ICalPeriod {
     fn parse(value: &str, params: &ICalParameterMap) -> anyhow::Result<Self> {
+        Self::parse_in(value, params, &ICalTimeZones::default())
+    }
+
+    fn parse_in(value: &str, params: &ICalParameterMap, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         let parts: (&str, &str) = value.split_once('/').ok_or(anyhow!("Period missing /"))?;
-        let start = ICalDateTime::parse(parts.0, params)
+        let start = ICalDateTime::parse_in(parts.0, params, tzs)
             .context("Parsing period start")?;
 
-        match ICalDateTime::parse(parts.1, params) {
+        match ICalDateTime::parse_in(parts.1, params, tzs) {
             Ok(end) => Ok(ICalPeriod::new(start, EndOrDuration::End(end))),
             Err(_) => {
                 let duration = ICalDuration::parse(parts.1, params)
@@ -49,15 +55,10 @@ impl ICalPeriod {
         }
     }
 
-    pub fn calc_end(&mut self) -> ICalDateTime {
+    pub fn calc_end(&self) -> ICalDateTime {
         match &self.end_or_duration {
             EndOrDuration::End(dt) => dt.clone(),
-            EndOrDuration::Duration(dur) => {
-                match self.start {
-                    ICalDateTime::Local(dt) => ICalDateTime::Local(dt.clone() + dur.clone()),
-                    ICalDateTime::Zoned(dt) => ICalDateTime::Zoned(dt.clone() + dur.clone()),
-                }
-            },
+            EndOrDuration::Duration(dur) => dur.add_to(&self.start),
         }
     }
 }
@@ -67,8 +68,12 @@ pub type ICalPeriodList = Vec<ICalPeriod>;
 
 impl ICalValueTrait for ICalPeriodList {
     fn parse(values: &str, params: &ICalParameterMap) -> anyhow::Result<Self> {
+        Self::parse_in(values, params, &ICalTimeZones::default())
+    }
+
+    fn parse_in(values: &str, params: &ICalParameterMap, tzs: &ICalTimeZones) -> anyhow::Result<Self> {
         values.split(',').try_fold(Vec::new(), |mut acc, value| {
-            acc.push(ICalPeriod::parse(value, params)?);
+            acc.push(ICalPeriod::parse_in(value, params, tzs)?);
             Ok(acc)
         })
     }
@@ -80,7 +85,6 @@ impl ICalValueTrait for ICalPeriodList {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
     use chrono::TimeZone;
 
     use chrono::DateTime;
@@ -95,7 +99,7 @@ mod tests {
         let value = "19970101T180000Z/19970102T070000Z";
         let start = Tz::UTC.with_ymd_and_hms(1997, 1, 1, 18, 0, 0).unwrap();
         let end = Tz::UTC.with_ymd_and_hms(1997, 1, 2, 7, 0, 0).unwrap();
-        assert_period(value, ICalDateTime::Zoned(start), ICalDateTime::Zoned(end));
+        assert_period(value, start.into(), end.into());
     }
 
     #[test]
@@ -105,13 +109,13 @@ mod tests {
         let value = "19970101T180000Z/PT2H30M";
         let start: DateTime<Tz> = Tz::UTC.with_ymd_and_hms(1997, 1, 1, 18, 0, 0).unwrap();
         let end: DateTime<Tz> = Tz::UTC.with_ymd_and_hms(1997, 1, 1, 20, 30, 0).unwrap();
-        assert_period(value, ICalDateTime::Zoned(start), ICalDateTime::Zoned(end));
+        assert_period(value, start.into(), end.into());
     }
 
     fn assert_period(value: &str, start: ICalDateTime, end: ICalDateTime) {
-        let per = ICalPeriod::parse(value, &HashMap::new()).expect("Failed to parse!");
+        let per = ICalPeriod::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
         assert_eq!(per.start, start);
-        assert_eq!(per.end, end);
+        assert_eq!(per.calc_end(), end);
         let s = ICalValueTrait::serialize(&per);
         assert_eq!(s, value);
     }
@@ -1,19 +1,22 @@
-use std::str::FromStr;
+use std::{fmt::Display, str::FromStr};
 
 use crate::property::ICalParameterMap;
 use super::ICalValueTrait;
 use super::{date::ICalDate, datetime::ICalDateTime};
 use anyhow::{anyhow, Context, bail};
 
+pub mod occurrences;
+
 //TODO Helper functions to make and use Recur easily
 
 ///RFC 5545 3.3.10 Recurrence Rule = rule ** ;
 #[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ICalRecur {
     pub freq: Frequency,
     pub until: Option<DateOrDateTime>,
-    pub count: Option<u8>,
-    pub interval: Option<u8>,
+    pub count: Option<u32>,
+    pub interval: Option<u32>,
     ///0-60
     pub bysecond: Vec<u8>,
     ///0-59
@@ -35,11 +38,13 @@ pub struct ICalRecur {
     pub wkst: Option<Weekday>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Frequency {
     Secondly,
     Minutely,
     Hourly,
+    #[default]
     Daily,
     Weekly,
     Monthly,
@@ -47,6 +52,7 @@ pub enum Frequency {
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Weekday {
     Sunday,
     Monday,
@@ -61,12 +67,14 @@ pub enum Weekday {
 /// the MONTHLY or YEARLY "RRULE" For example, within a MONTHLY rule, +1MO (or 1MO) represents the
 /// first Monday in the month and -1MO represents the last
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ByDay {
     pub ordwk: Option<i8>,
     pub weekday: Weekday
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DateOrDateTime {
     Date(ICalDate),
     DateTime(ICalDateTime)
@@ -134,12 +142,44 @@ impl ICalValueTrait for ICalRecur {
 }
 
 impl ICalRecur {
+    /// checks that every rule part is within the ranges of RFC 5545 3.3.10
     pub fn validate(&self) -> anyhow::Result<()> {
+        if self.interval == Some(0) {
+            bail!("Recur INTERVAL must be a positive integer");
+        }
+        if self.until.is_some() && self.count.is_some() {
+            bail!("Recur UNTIL and COUNT must not occur in the same rule");
+        }
+        validate_range("BYSECOND", &self.bysecond, 0, 60, false)?;
+        validate_range("BYMINUTE", &self.byminute, 0, 59, false)?;
+        validate_range("BYHOUR", &self.byhour, 0, 23, false)?;
+        validate_range("BYMONTHDAY", &self.bymonthday, 1, 31, true)?;
+        validate_range("BYYEARDAY", &self.byyearday, 1, 366, true)?;
+        validate_range("BYWEEKNO", &self.byweekno, 1, 53, true)?;
+        validate_range("BYMONTH", &self.bymonth, 1, 12, false)?;
+        validate_range("BYSETPOS", &self.bysetpos, 1, 366, true)?;
+        let ordwks: Vec<i8> = self.byday.iter().filter_map(|d| d.ordwk).collect();
+        validate_range("BYDAY", &ordwks, 1, 53, true)?;
         Ok(())
     }
 }
 
-fn serialize_opt<T: ToString>(s: &mut String, name: &str, r: &Option<T>) {
+/// signed ranges allow both +/- min..=max
+fn validate_range<T>(name: &str, values: &[T], min: i64, max: i64, signed: bool) -> anyhow::Result<()>
+where
+    T: Copy + Into<i64>,
+{
+    for value in values {
+        let value: i64 = (*value).into();
+        let abs = if signed { value.abs() } else { value };
+        if abs < min || abs > max {
+            bail!("Recur {name} value {value} out of range");
+        }
+    }
+    Ok(())
+}
+
+fn serialize_opt<T: Display>(s: &mut String, name: &str, r: &Option<T>) {
     if let Some(val) = r {
         s.push_str(name);
         s.push('=');
@@ -148,8 +188,8 @@ fn serialize_opt<T: ToString>(s: &mut String, name: &str, r: &Option<T>) {
     }
 }
 
-fn serialize_vec<T: ToString>(s: &mut String, name: &str, r: &Vec<T>) {
-    if r.len() > 0 {
+fn serialize_vec<T: Display>(s: &mut String, name: &str, r: &[T]) {
+    if !r.is_empty() {
         s.push_str(name);
         s.push('=');
         for (i, num) in r.iter().enumerate() {
@@ -194,9 +234,9 @@ impl FromStr for Frequency {
     }
 }
 
-impl ToString for Frequency {
-    fn to_string(&self) -> String {
-        match &self {
+impl Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match &self {
             Self::Secondly => "SECONDLY",
             Self::Minutely => "MINUTELY",
             Self::Hourly => "HOURLY",
@@ -204,7 +244,7 @@ impl ToString for Frequency {
             Self::Weekly => "WEEKLY",
             Self::Monthly => "MONTHLY",
             Self::Yearly => "YEARLY",
-        }.to_string()
+        })
     }
 }
 
@@ -224,9 +264,9 @@ impl FromStr for Weekday {
     }
 }
 
-impl ToString for Weekday {
-    fn to_string(&self) -> String {
-        match &self {
+impl Display for Weekday {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match &self {
             Self::Sunday => "SU",
             Self::Monday => "MO",
             Self::Tuesday => "TU",
@@ -234,7 +274,7 @@ impl ToString for Weekday {
             Self::Thursday => "TH",
             Self::Friday => "FR",
             Self::Saturday => "SA",
-        }.to_string()
+        })
     }
 }
 
@@ -251,21 +291,15 @@ impl DateOrDateTime {
     }
 }
 
-impl ToString for DateOrDateTime {
-    fn to_string(&self) -> String {
+impl Display for DateOrDateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match &self {
-            Self::Date(d) => d.serialize(),
-            Self::DateTime(dt) => dt.serialize(),
+            Self::Date(d) => f.write_str(&d.serialize()),
+            Self::DateTime(dt) => f.write_str(&dt.serialize()),
         }
     }
 }
 
-impl Default for Frequency {
-    fn default() -> Self {
-        Self::Daily
-    }
-}
-
 impl FromStr for ByDay {
     type Err = anyhow::Error;
 
@@ -277,21 +311,22 @@ impl FromStr for ByDay {
             })
         }
         else {
-            let parts: Vec<&str> = s.splitn(2, char::is_alphabetic).collect();
+            let (ordwk, weekday) = s.split_at_checked(s.len() - 2)
+                .ok_or(anyhow!("Invalid weekday"))?;
             Ok(Self {
-                ordwk: Some(parts[0].parse()?),
-                weekday: Weekday::from_str(parts[1])?,
+                ordwk: Some(ordwk.parse()?),
+                weekday: Weekday::from_str(weekday)?,
             })
         }
     }
 }
 
-impl ToString for ByDay {
-    fn to_string(&self) -> String {
+impl Display for ByDay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(ordwk) = &self.ordwk {
-            return ordwk.to_string() + &self.weekday.to_string()
+            write!(f, "{}", ordwk)?;
         }
-        return self.weekday.to_string()
+        write!(f, "{}", self.weekday)
     }
 }
 
@@ -306,8 +341,6 @@ impl ByDay {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
-
     use crate::values::recur::*;
 
     #[test]
@@ -325,7 +358,7 @@ mod tests {
             bysetpos: vec![-1],
             ..Default::default()
         };
-        let result = ICalRecur::parse(value, &HashMap::new()).expect("Failed to parse!");
+        let result = ICalRecur::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
         assert_eq!(result, expected);
         let s = ICalValueTrait::serialize(&result);
         assert_eq!(s, value);
@@ -0,0 +1,684 @@
+use std::collections::VecDeque;
+
+use chrono::{Datelike, Days, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike};
+use crate::{timezone::ICalTimeZone, values::{date::ICalDate, datetime::{resolve_local, ICalDateTime}}};
+use super::{DateOrDateTime, Frequency, ICalRecur, Weekday};
+
+/// instances are not generated past this year (DATE-TIME years are 4 digits)
+const MAX_YEAR: i64 = 9999;
+
+impl ICalRecur {
+    /// Iterates the instances of this rule for the given DTSTART (RFC 5545 3.3.10)
+    /// NOTE: DTSTART is always the first instance and counts towards COUNT,
+    /// even if it does not match the rule
+    /// Zoned instances are expanded in local time, so they keep their wall clock
+    /// time across DST transitions
+    pub fn occurrences(&self, dtstart: &ICalDateTime) -> Occurrences {
+        let (local, tz) = match dtstart {
+            ICalDateTime::Local(dt) => (*dt, None),
+            ICalDateTime::Zoned(dt) => (dt.naive_local(), Some(dt.timezone())),
+        };
+        let until = self.until.as_ref().map(|until| match until {
+            DateOrDateTime::Date(d) => end_of_day(d),
+            DateOrDateTime::DateTime(ICalDateTime::Local(dt)) => *dt,
+            DateOrDateTime::DateTime(ICalDateTime::Zoned(dt)) => match &tz {
+                Some(tz) => dt.with_timezone(tz).naive_local(),
+                None => dt.naive_local(),
+            },
+        });
+        Occurrences {
+            iter: RecurIter::new(self, local, until),
+            tz,
+        }
+    }
+
+    /// Iterates the instances of this rule for a DATE valued DTSTART
+    pub fn date_occurrences(&self, dtstart: &ICalDate) -> impl Iterator<Item = ICalDate> {
+        let until = self.until.as_ref().map(|until| match until {
+            DateOrDateTime::Date(d) => end_of_day(d),
+            DateOrDateTime::DateTime(dt) => end_of_day(&dt.naive_local().date()),
+        });
+        RecurIter::new(self, dtstart.and_time(NaiveTime::MIN), until)
+            .map(|dt| dt.date())
+    }
+
+    /// Iterates the instances of this rule in local (wall clock) time
+    /// UNTIL must already be converted to the same local time
+    pub fn local_occurrences(&self, dtstart: NaiveDateTime, until: Option<NaiveDateTime>) -> RecurIter {
+        RecurIter::new(self, dtstart, until)
+    }
+}
+
+fn end_of_day(date: &NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(23, 59, 59).unwrap()
+}
+
+/// Instances of a RECUR rule, see [ICalRecur::occurrences]
+pub struct Occurrences {
+    iter: RecurIter,
+    tz: Option<ICalTimeZone>,
+}
+
+impl Iterator for Occurrences {
+    type Item = ICalDateTime;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let local = self.iter.next()?;
+        Some(match &self.tz {
+            Some(tz) => ICalDateTime::Zoned(resolve_local(tz, &local)),
+            None => ICalDateTime::Local(local),
+        })
+    }
+}
+
+/// Local time instances of a RECUR rule
+///
+/// Each period (year, month, week, ... depending on FREQ) is expanded into a
+/// candidate set following the table in RFC 5545 3.3.10: day level BYxxx parts
+/// filter the days of the period, time level parts either expand or limit
+/// depending on FREQ, then BYSETPOS selects from the sorted set.
+pub struct RecurIter {
+    /// None if the rule can never match
+    rule: Option<Expansion>,
+    dtstart: NaiveDateTime,
+    until: Option<NaiveDateTime>,
+    remaining: Option<u32>,
+    /// index of the next period to expand (multiplied by INTERVAL)
+    period: i64,
+    pending: VecDeque<NaiveDateTime>,
+    started: bool,
+    done: bool,
+}
+
+impl RecurIter {
+    fn new(recur: &ICalRecur, dtstart: NaiveDateTime, until: Option<NaiveDateTime>) -> Self {
+        let rule = Expansion::new(recur, &dtstart);
+        Self {
+            done: rule.is_none(),
+            rule,
+            dtstart,
+            until,
+            remaining: recur.count,
+            period: 0,
+            pending: VecDeque::new(),
+            started: false,
+        }
+    }
+
+    fn emit(&mut self, dt: NaiveDateTime) -> Option<NaiveDateTime> {
+        let past_until = self.until.is_some_and(|until| dt > until);
+        if past_until || self.remaining == Some(0) {
+            self.done = true;
+            return None
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+        Some(dt)
+    }
+
+    /// expands the next period into pending
+    fn fill(&mut self) {
+        let Some(rule) = &self.rule else {
+            self.done = true;
+            return
+        };
+        let start = self.dtstart;
+        let period = self.period;
+        let k = period * rule.interval;
+        self.period += 1;
+
+        let mut set = match rule.freq {
+            Frequency::Yearly => {
+                let year = start.year() as i64 + k;
+                if year > MAX_YEAR {
+                    self.done = true;
+                    return
+                }
+                match rule.bymonth.is_empty() {
+                    true => rule.expand_days(month_days(year as i32, 1..=12)),
+                    false => rule.expand_days(month_days(year as i32, rule.bymonth.iter().copied())),
+                }
+            },
+            Frequency::Monthly => {
+                let months = start.year() as i64 * 12 + start.month0() as i64 + k;
+                let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+                if year > MAX_YEAR {
+                    self.done = true;
+                    return
+                }
+                rule.expand_days(month_days(year as i32, std::iter::once(month)))
+            },
+            Frequency::Weekly => {
+                let first = week_start(start.date(), rule.wkst) + Days::new(7 * k as u64);
+                if first.year() as i64 > MAX_YEAR {
+                    self.done = true;
+                    return
+                }
+                rule.expand_days(first.iter_days().take(7))
+            },
+            Frequency::Daily => {
+                let day = start.date() + Days::new(k as u64);
+                if day.year() as i64 > MAX_YEAR {
+                    self.done = true;
+                    return
+                }
+                rule.expand_days(std::iter::once(day))
+            },
+            Frequency::Hourly | Frequency::Minutely | Frequency::Secondly => {
+                let (unit, base) = match rule.freq {
+                    Frequency::Hourly => (3600, start.date().and_hms_opt(start.hour(), 0, 0)),
+                    Frequency::Minutely => (60, start.date().and_hms_opt(start.hour(), start.minute(), 0)),
+                    _ => (1, Some(start)),
+                };
+                let (step, base) = (unit * rule.interval, base.unwrap());
+                let dt = base + TimeDelta::seconds(step * period);
+                if dt.year() as i64 > MAX_YEAR {
+                    self.done = true;
+                    return
+                }
+                if !rule.day_matches(dt.date()) {
+                    //skip straight to the first period of the next day
+                    let next_day = (dt.date() + Days::new(1)).and_time(NaiveTime::MIN);
+                    let to_next_day = (next_day - base).num_seconds();
+                    let steps = (to_next_day + step - 1) / step;
+                    self.period = self.period.max(steps);
+                    return
+                }
+                rule.expand_time(dt)
+            },
+        };
+
+        rule.apply_setpos(&mut set);
+        self.pending.extend(set);
+    }
+}
+
+impl Iterator for RecurIter {
+    type Item = NaiveDateTime;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if !self.started && !self.done {
+            self.started = true;
+            return self.emit(self.dtstart)
+        }
+        while !self.done {
+            match self.pending.pop_front() {
+                //DTSTART was already emitted
+                Some(dt) if dt <= self.dtstart => continue,
+                Some(dt) => return self.emit(dt),
+                None => self.fill(),
+            }
+        }
+        None
+    }
+}
+
+/// Normalized form of [ICalRecur] with defaults taken from DTSTART
+struct Expansion {
+    freq: Frequency,
+    interval: i64,
+    wkst: chrono::Weekday,
+    bymonth: Vec<u32>,
+    byweekno: Vec<i64>,
+    byyearday: Vec<i64>,
+    bymonthday: Vec<i64>,
+    byweekday: Vec<chrono::Weekday>,
+    bynweekday: Vec<(i64, chrono::Weekday)>,
+    byhour: Vec<u32>,
+    byminute: Vec<u32>,
+    bysecond: Vec<u32>,
+    bysetpos: Vec<i64>,
+    /// time of day expansion for FREQ >= DAILY
+    times: Vec<NaiveTime>,
+}
+
+impl Expansion {
+    /// returns None if a rule part has no usable values (ex. BYSECOND=60)
+    /// or BYSETPOS is past every period set (ex. FREQ=SECONDLY;BYSETPOS=2)
+    fn new(recur: &ICalRecur, dtstart: &NaiveDateTime) -> Option<Self> {
+        let mut rule = Self {
+            freq: recur.freq.clone(),
+            interval: recur.interval.unwrap_or(1).max(1) as i64,
+            wkst: recur.wkst.as_ref().map(|w| w.into()).unwrap_or(chrono::Weekday::Mon),
+            bymonth: in_range(&recur.bymonth, 1, 12, false)?,
+            byweekno: in_range(&recur.byweekno, 1, 53, true)?,
+            byyearday: in_range(&recur.byyearday, 1, 366, true)?,
+            bymonthday: in_range(&recur.bymonthday, 1, 31, true)?,
+            byhour: in_range(&recur.byhour, 0, 23, false)?,
+            byminute: in_range(&recur.byminute, 0, 59, false)?,
+            //leap seconds (60) are not representable
+            bysecond: in_range(&recur.bysecond, 0, 59, false)?,
+            bysetpos: in_range(&recur.bysetpos, 1, 366, true)?,
+            byweekday: vec![],
+            bynweekday: vec![],
+            times: vec![],
+        };
+
+        //ordinal BYDAY values only apply to MONTHLY and YEARLY (without BYWEEKNO)
+        let use_ordinals = match rule.freq {
+            Frequency::Monthly => true,
+            Frequency::Yearly => rule.byweekno.is_empty(),
+            _ => false,
+        };
+        for byday in &recur.byday {
+            match byday.ordwk {
+                Some(n) if use_ordinals && n != 0 => rule.bynweekday.push((n as i64, (&byday.weekday).into())),
+                _ => rule.byweekday.push((&byday.weekday).into()),
+            }
+        }
+
+        let no_day_parts = rule.byweekno.is_empty() && rule.byyearday.is_empty()
+            && rule.bymonthday.is_empty() && recur.byday.is_empty();
+        if no_day_parts {
+            match rule.freq {
+                Frequency::Yearly => {
+                    if rule.bymonth.is_empty() {
+                        rule.bymonth.push(dtstart.month());
+                    }
+                    rule.bymonthday.push(dtstart.day() as i64);
+                },
+                Frequency::Monthly => rule.bymonthday.push(dtstart.day() as i64),
+                Frequency::Weekly => rule.byweekday.push(dtstart.weekday()),
+                _ => {},
+            }
+        }
+
+        let rank = freq_rank(&rule.freq);
+        if rule.byhour.is_empty() && rank > freq_rank(&Frequency::Hourly) {
+            rule.byhour.push(dtstart.hour());
+        }
+        if rule.byminute.is_empty() && rank > freq_rank(&Frequency::Minutely) {
+            rule.byminute.push(dtstart.minute());
+        }
+        if rule.bysecond.is_empty() && rank > freq_rank(&Frequency::Secondly) {
+            rule.bysecond.push(dtstart.second());
+        }
+
+        for h in &rule.byhour {
+            for m in &rule.byminute {
+                for s in &rule.bysecond {
+                    rule.times.push(NaiveTime::from_hms_opt(*h, *m, *s).unwrap());
+                }
+            }
+        }
+        rule.times.sort();
+
+        //BYSETPOS can never select anything past the largest possible period set
+        let max_set = match rule.freq {
+            Frequency::Secondly => 1,
+            Frequency::Minutely => rule.bysecond.len(),
+            Frequency::Hourly => rule.byminute.len() * rule.bysecond.len(),
+            Frequency::Daily => rule.times.len(),
+            Frequency::Weekly => 7 * rule.times.len(),
+            Frequency::Monthly => 31 * rule.times.len(),
+            Frequency::Yearly => 366 * rule.times.len(),
+        } as i64;
+        if !rule.bysetpos.is_empty() && rule.bysetpos.iter().all(|pos| pos.abs() > max_set) {
+            return None
+        }
+        Some(rule)
+    }
+
+    /// FREQ >= DAILY: every matching day at every time of day
+    fn expand_days(&self, days: impl Iterator<Item = NaiveDate>) -> Vec<NaiveDateTime> {
+        days.filter(|d| self.day_matches(*d))
+            .flat_map(|d| self.times.iter().map(move |t| d.and_time(*t)))
+            .collect()
+    }
+
+    /// FREQ < DAILY: coarser time parts limit, finer parts expand
+    fn expand_time(&self, dt: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let limited = |values: &[u32], v: u32| values.is_empty() || values.contains(&v);
+        let date = dt.date();
+        let (hour, minute, second) = (dt.hour(), dt.minute(), dt.second());
+        let to_dt = |h: u32, m: u32, s: u32| date.and_hms_opt(h, m, s).unwrap();
+        if !limited(&self.byhour, hour) {
+            return vec![]
+        }
+        match self.freq {
+            Frequency::Hourly => self.byminute.iter()
+                .flat_map(|m| self.bysecond.iter().map(move |s| to_dt(hour, *m, *s)))
+                .collect(),
+            Frequency::Minutely if limited(&self.byminute, minute) => self.bysecond.iter()
+                .map(|s| to_dt(hour, minute, *s))
+                .collect(),
+            Frequency::Secondly if limited(&self.byminute, minute) && limited(&self.bysecond, second) => {
+                vec![dt]
+            },
+            _ => vec![],
+        }
+    }
+
+    fn day_matches(&self, d: NaiveDate) -> bool {
+        let has = |values: &[i64], pos: i64, len: i64| values.contains(&pos) || values.contains(&(pos - len - 1));
+
+        if !self.bymonth.is_empty() && !self.bymonth.contains(&d.month()) {
+            return false
+        }
+        if !self.byweekno.is_empty() && !self.weekno_matches(d) {
+            return false
+        }
+        if !self.byyearday.is_empty() && !has(&self.byyearday, d.ordinal() as i64, days_in_year(d.year())) {
+            return false
+        }
+        if !self.bymonthday.is_empty() && !has(&self.bymonthday, d.day() as i64, days_in_month(d)) {
+            return false
+        }
+        if self.byweekday.is_empty() && self.bynweekday.is_empty() {
+            return true
+        }
+        if self.byweekday.contains(&d.weekday()) {
+            return true
+        }
+
+        //nth weekday in the month for MONTHLY or YEARLY+BYMONTH, otherwise in the year
+        let (pos, len) = match self.freq == Frequency::Monthly || !self.bymonth.is_empty() {
+            true => (d.day() as i64, days_in_month(d)),
+            false => (d.ordinal() as i64, days_in_year(d.year())),
+        };
+        let (nth, nth_last) = ((pos - 1) / 7 + 1, -((len - pos) / 7 + 1));
+        self.bynweekday.iter()
+            .any(|(n, wd)| *wd == d.weekday() && (*n == nth || *n == nth_last))
+    }
+
+    /// RFC 5545 3.3.10: week 1 is the first week with at least 4 days in the year
+    fn weekno_matches(&self, d: NaiveDate) -> bool {
+        let year = d.year();
+        let start = week_one_start(year, self.wkst);
+        let next_start = week_one_start(year + 1, self.wkst);
+        let has = |n: i64| self.byweekno.contains(&n);
+        if d < start {
+            let weeks = weeks_in_year(year - 1, self.wkst);
+            return has(weeks) || has(-1)
+        }
+        if d >= next_start {
+            let weeks = weeks_in_year(year + 1, self.wkst);
+            return has(1) || has(-weeks)
+        }
+        let weeks = (next_start - start).num_days() / 7;
+        let n = (d - start).num_days() / 7 + 1;
+        has(n) || has(n - weeks - 1)
+    }
+
+    fn apply_setpos(&self, set: &mut Vec<NaiveDateTime>) {
+        set.sort();
+        set.dedup();
+        if self.bysetpos.is_empty() {
+            return
+        }
+        let len = set.len() as i64;
+        let mut selected: Vec<NaiveDateTime> = self.bysetpos.iter()
+            .filter_map(|pos| {
+                let idx = if *pos > 0 { pos - 1 } else { len + pos };
+                (0..len).contains(&idx).then(|| set[idx as usize])
+            })
+            .collect();
+        selected.sort();
+        selected.dedup();
+        *set = selected;
+    }
+}
+
+/// values outside of min..=max (or -max..=-min when signed) are dropped
+/// returns None if values were given but none were usable
+fn in_range<T, R>(values: &[T], min: i64, max: i64, signed: bool) -> Option<Vec<R>>
+where
+    T: Copy + Into<i64>,
+    R: TryFrom<i64>,
+{
+    let mut result: Vec<R> = values.iter()
+        .map(|v| (*v).into())
+        .filter(|v: &i64| {
+            let abs = if signed { v.abs() } else { *v };
+            abs >= min && abs <= max
+        })
+        .filter_map(|v| R::try_from(v).ok())
+        .collect();
+    if result.is_empty() && !values.is_empty() {
+        return None
+    }
+    result.shrink_to_fit();
+    Some(result)
+}
+
+/// every day of the given months
+fn month_days(year: i32, months: impl Iterator<Item = u32>) -> impl Iterator<Item = NaiveDate> {
+    months.flat_map(move |month| {
+        let first = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+        first.iter_days().take_while(move |d| d.month() == month)
+    })
+}
+
+fn freq_rank(freq: &Frequency) -> u8 {
+    match freq {
+        Frequency::Secondly => 0,
+        Frequency::Minutely => 1,
+        Frequency::Hourly => 2,
+        Frequency::Daily => 3,
+        Frequency::Weekly => 4,
+        Frequency::Monthly => 5,
+        Frequency::Yearly => 6,
+    }
+}
+
+fn week_start(date: NaiveDate, wkst: chrono::Weekday) -> NaiveDate {
+    date - Days::new(date.weekday().days_since(wkst) as u64)
+}
+
+fn week_one_start(year: i32, wkst: chrono::Weekday) -> NaiveDate {
+    let jan1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+    let offset = jan1.weekday().days_since(wkst) as u64;
+    if offset <= 3 {
+        jan1 - Days::new(offset)
+    } else {
+        jan1 + Days::new(7 - offset)
+    }
+}
+
+fn weeks_in_year(year: i32, wkst: chrono::Weekday) -> i64 {
+    (week_one_start(year + 1, wkst) - week_one_start(year, wkst)).num_days() / 7
+}
+
+fn days_in_year(year: i32) -> i64 {
+    NaiveDate::from_ymd_opt(year, 12, 31).unwrap().ordinal() as i64
+}
+
+fn days_in_month(date: NaiveDate) -> i64 {
+    let (year, month) = match date.month() {
+        12 => (date.year() + 1, 1),
+        m => (date.year(), m + 1),
+    };
+    let next = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    next.pred_opt().unwrap().day() as i64
+}
+
+impl From<&Weekday> for chrono::Weekday {
+    fn from(value: &Weekday) -> Self {
+        match value {
+            Weekday::Sunday => Self::Sun,
+            Weekday::Monday => Self::Mon,
+            Weekday::Tuesday => Self::Tue,
+            Weekday::Wednesday => Self::Wed,
+            Weekday::Thursday => Self::Thu,
+            Weekday::Friday => Self::Fri,
+            Weekday::Saturday => Self::Sat,
+        }
+    }
+}
+
+impl From<chrono::Weekday> for Weekday {
+    fn from(value: chrono::Weekday) -> Self {
+        match value {
+            chrono::Weekday::Sun => Self::Sunday,
+            chrono::Weekday::Mon => Self::Monday,
+            chrono::Weekday::Tue => Self::Tuesday,
+            chrono::Weekday::Wed => Self::Wednesday,
+            chrono::Weekday::Thu => Self::Thursday,
+            chrono::Weekday::Fri => Self::Friday,
+            chrono::Weekday::Sat => Self::Saturday,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::property::ICalParameterMap;
+
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone};
+    use chrono_tz::Tz;
+
+    use crate::values::{datetime::ICalDateTime, recur::ICalRecur, ICalValueTrait};
+
+    /// examples from RFC 5545 3.8.5.3
+    fn assert_local(rule: &str, dtstart: &str, take: usize, expected: &[&str]) {
+        let recur = ICalRecur::parse(rule, &ICalParameterMap::new()).expect("Failed to parse!");
+        let dtstart = NaiveDateTime::parse_from_str(dtstart, "%Y%m%dT%H%M%S").unwrap();
+        let result: Vec<String> = recur.local_occurrences(dtstart, None)
+            .take(take)
+            .map(|dt| dt.format("%Y%m%dT%H%M%S").to_string())
+            .collect();
+        assert_eq!(result, expected, "{rule}");
+    }
+
+    #[test]
+    fn test_daily_count() {
+        assert_local("FREQ=DAILY;COUNT=3", "19970902T090000", 10,
+            &["19970902T090000", "19970903T090000", "19970904T090000"]);
+    }
+
+    #[test]
+    fn test_every_other_day() {
+        assert_local("FREQ=DAILY;INTERVAL=2", "19970902T090000", 3,
+            &["19970902T090000", "19970904T090000", "19970906T090000"]);
+    }
+
+    #[test]
+    fn test_weekly_byday() {
+        assert_local("FREQ=WEEKLY;INTERVAL=2;WKST=SU;BYDAY=MO,WE,FR;COUNT=6", "19970901T090000", 10,
+            &["19970901T090000", "19970903T090000", "19970905T090000",
+              "19970915T090000", "19970917T090000", "19970919T090000"]);
+    }
+
+    #[test]
+    fn test_weekly_wkst() {
+        assert_local("FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=MO", "19970805T090000", 10,
+            &["19970805T090000", "19970810T090000", "19970819T090000", "19970824T090000"]);
+        assert_local("FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=SU", "19970805T090000", 10,
+            &["19970805T090000", "19970817T090000", "19970819T090000", "19970831T090000"]);
+    }
+
+    #[test]
+    fn test_monthly_nth_weekday() {
+        assert_local("FREQ=MONTHLY;COUNT=6;BYDAY=1FR,-1FR", "19970905T090000", 10,
+            &["19970905T090000", "19970926T090000", "19971003T090000",
+              "19971031T090000", "19971107T090000", "19971128T090000"]);
+    }
+
+    #[test]
+    fn test_monthly_negative_monthday() {
+        assert_local("FREQ=MONTHLY;BYMONTHDAY=-3", "19970928T090000", 4,
+            &["19970928T090000", "19971029T090000", "19971128T090000", "19971229T090000"]);
+    }
+
+    #[test]
+    fn test_friday_13th() {
+        assert_local("FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13", "19980213T090000", 4,
+            &["19980213T090000", "19980313T090000", "19981113T090000", "19990813T090000"]);
+    }
+
+    #[test]
+    fn test_yearly_byweekno() {
+        assert_local("FREQ=YEARLY;BYWEEKNO=20;BYDAY=MO", "19970512T090000", 3,
+            &["19970512T090000", "19980511T090000", "19990517T090000"]);
+    }
+
+    #[test]
+    fn test_yearly_byyearday() {
+        assert_local("FREQ=YEARLY;INTERVAL=3;COUNT=5;BYYEARDAY=1,100,200", "19970101T090000", 10,
+            &["19970101T090000", "19970410T090000", "19970719T090000",
+              "20000101T090000", "20000409T090000"]);
+    }
+
+    #[test]
+    fn test_yearly_nth_weekday_of_year() {
+        assert_local("FREQ=YEARLY;BYDAY=20MO", "19970519T090000", 3,
+            &["19970519T090000", "19980518T090000", "19990517T090000"]);
+    }
+
+    #[test]
+    fn test_leap_day() {
+        assert_local("FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=29", "20000229T090000", 3,
+            &["20000229T090000", "20040229T090000", "20080229T090000"]);
+    }
+
+    #[test]
+    fn test_bysetpos() {
+        //last work day of the month
+        assert_local("FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-1", "19970930T090000", 3,
+            &["19970930T090000", "19971031T090000", "19971128T090000"]);
+        //3rd instance of Tuesday, Wednesday or Thursday
+        assert_local("FREQ=MONTHLY;COUNT=3;BYDAY=TU,WE,TH;BYSETPOS=3", "19970904T090000", 10,
+            &["19970904T090000", "19971007T090000", "19971106T090000"]);
+    }
+
+    #[test]
+    fn test_hourly_byminute() {
+        assert_local("FREQ=MINUTELY;INTERVAL=20;BYHOUR=9,10", "19970902T090000", 7,
+            &["19970902T090000", "19970902T092000", "19970902T094000",
+              "19970902T100000", "19970902T102000", "19970902T104000",
+              "19970903T090000"]);
+        assert_local("FREQ=DAILY;BYHOUR=9,10,11;BYMINUTE=0,30", "19970902T090000", 4,
+            &["19970902T090000", "19970902T093000", "19970902T100000", "19970902T103000"]);
+    }
+
+    #[test]
+    fn test_hourly_skips_days() {
+        assert_local("FREQ=HOURLY;INTERVAL=5;BYDAY=MO", "19970902T090000", 4,
+            &["19970902T090000", "19970908T000000", "19970908T050000", "19970908T100000"]);
+    }
+
+    #[test]
+    fn test_dtstart_counts() {
+        //DTSTART is a Tuesday but the rule only matches Mondays
+        assert_local("FREQ=WEEKLY;BYDAY=MO;COUNT=2", "19970902T090000", 10,
+            &["19970902T090000", "19970908T090000"]);
+    }
+
+    #[test]
+    fn test_impossible_rule_ends() {
+        assert_local("FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30", "19970902T090000", 10,
+            &["19970902T090000"]);
+        //each period only holds one instance, so BYSETPOS can't select anything
+        assert_local("FREQ=SECONDLY;BYSETPOS=2", "19970902T090000", 10, &[]);
+        assert_local("FREQ=MINUTELY;BYSETPOS=-2", "19970902T090000", 10, &[]);
+    }
+
+    #[test]
+    fn test_until_utc() {
+        let recur = ICalRecur::parse("FREQ=DAILY;UNTIL=19971224T000000Z", &ICalParameterMap::new()).unwrap();
+        let dtstart = Tz::America__New_York.with_ymd_and_hms(1997, 12, 20, 9, 0, 0).unwrap();
+        let count = recur.occurrences(&dtstart.into()).count();
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn test_zoned_keeps_wall_time() {
+        let recur = ICalRecur::parse("FREQ=DAILY;COUNT=3", &ICalParameterMap::new()).unwrap();
+        let dtstart = Tz::America__New_York.with_ymd_and_hms(2024, 3, 9, 9, 0, 0).unwrap();
+        let result: Vec<ICalDateTime> = recur.occurrences(&dtstart.into()).collect();
+        let expected: Vec<ICalDateTime> = (9..=11)
+            .map(|d| Tz::America__New_York.with_ymd_and_hms(2024, 3, d, 9, 0, 0).unwrap().into())
+            .collect();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_date_occurrences() {
+        let recur = ICalRecur::parse("FREQ=YEARLY;UNTIL=20000101", &ICalParameterMap::new()).unwrap();
+        let dtstart = NaiveDate::from_ymd_opt(1997, 1, 1).unwrap();
+        let result: Vec<NaiveDate> = recur.date_occurrences(&dtstart).collect();
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[3], NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
+    }
+}
@@ -5,11 +5,11 @@ pub type ICalText = String;
 
 impl ICalValueTrait for ICalText {
     fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
-        Ok(value.to_string())
+        Ok(unescape(value))
     }
 
     fn serialize(&self) -> String {
-        self.to_string()
+        escape(self)
     }
 }
 
@@ -17,16 +17,99 @@ pub type ICalTextList = Vec<String>;
 
 impl ICalValueTrait for ICalTextList {
     fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
-        Ok(value.split(',').map(|s| s.to_owned()).collect())
+        Ok(split_escaped(value, ',').into_iter().map(unescape).collect())
     }
 
     fn serialize(&self) -> String {
-        self.join(",")
+        self.iter().map(|s| escape(s)).collect::<Vec<String>>().join(",")
     }
 }
 
+/// RFC 5545 3.3.11: ESCAPED-CHAR = ("\\" / "\;" / "\," / "\N" / "\n")
+/// NOTE: other escaped characters are kept without the backslash
+pub(crate) fn unescape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// RFC 5545 3.3.11: escapes backslashes, semicolons, commas and newlines
+pub(crate) fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | ';' | ',' => {
+                result.push('\\');
+                result.push(c);
+            },
+            '\r' if chars.peek() == Some(&'\n') => {},
+            '\n' => result.push_str("\\n"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// splits on separators that aren't escaped with a backslash
+pub(crate) fn split_escaped(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut escaped) = (0, false);
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        }
+        else if c == '\\' {
+            escaped = true;
+        }
+        else if c == separator {
+            parts.push(&value[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
 impl From<&str> for ICalProperty {
     fn from(value: &str) -> Self {
         Self::from_value(ICalValue::Text(value.to_string()))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::values::text::*;
+
+    #[test]
+    fn test_text_escaping() {
+        assert_text("Meeting\\; bring laptop\\, charger\\nRoom 4\\\\B", "Meeting; bring laptop, charger\nRoom 4\\B");
+        assert_eq!(ICalText::parse("Line 1\\NLine 2", &ICalParameterMap::new()).unwrap(), "Line 1\nLine 2");
+        assert_eq!("a\r\nb".to_string().serialize(), "a\\nb");
+    }
+
+    #[test]
+    fn test_text_list_escaping() {
+        let value = "a\\,b,c\\\\,d\\;e";
+        let list = ICalTextList::parse(value, &ICalParameterMap::new()).unwrap();
+        assert_eq!(list, vec!["a,b", "c\\", "d;e"]);
+        assert_eq!(list.serialize(), value);
+    }
+
+    fn assert_text(value: &str, expected: &str) {
+        let result = ICalText::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
+        assert_eq!(result, expected);
+        assert_eq!(result.serialize(), value);
+    }
+}
@@ -9,6 +9,7 @@ use super::ICalValueTrait;
 
 /// RFC 5545 3.3.12
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ICalTime {
     pub time: NaiveTime,
     pub timezone: Option<Tz>
@@ -38,35 +39,33 @@ impl ICalValueTrait for ICalTime {
 #[cfg(test)]
 mod tests {
     use chrono::NaiveTime;
-    use std::collections::HashMap;
-
     use crate::values::time::*;
 
     #[test]
     fn test_datetime_local() {
         let value = "230000";
         let expected = NaiveTime::from_hms_opt(23, 0, 0).unwrap();
-        assert_time(value, &HashMap::new(), expected, None);
+        assert_time(value, &ICalParameterMap::new(), expected, None);
     }
 
     #[test]
     fn test_datetime_utc() {
         let value = "183005Z";
         let expected = NaiveTime::from_hms_opt(18, 30, 5).unwrap();
-        assert_time(value, &HashMap::new(), expected, Some(Tz::UTC));
+        assert_time(value, &ICalParameterMap::new(), expected, Some(Tz::UTC));
     }
 
     #[test]
     fn test_datetime_tz() {
-        let mut params = HashMap::new();
-        params.insert("TZID".to_string(), "America/New_York".to_string());
+        let mut params = ICalParameterMap::new();
+        params.insert("TZID", "America/New_York");
         let value = "013010";
         let expected = NaiveTime::from_hms_opt(1, 30, 10).unwrap();
         assert_time(value, &params, expected, Some(Tz::America__New_York));
     }
 
-    fn assert_time(value: &str, params: &HashMap<String, String>, expected_time: NaiveTime, expected_timezone: Option<Tz>) {
-        let icaltime = ICalTime::parse(value, &params).expect("Failed to parse!");
+    fn assert_time(value: &str, params: &ICalParameterMap, expected_time: NaiveTime, expected_timezone: Option<Tz>) {
+        let icaltime = ICalTime::parse(value, params).expect("Failed to parse!");
         assert_eq!(icaltime.time, expected_time);
         assert_eq!(icaltime.timezone, expected_timezone);
         let s = ICalValueTrait::serialize(&icaltime);
@@ -0,0 +1,49 @@
+use crate::property::ICalParameterMap;
+use super::ICalValueTrait;
+
+/// A value kept exactly as written: X- and IANA properties without a VALUE parameter (RFC 5545 3.8.8.1-2),
+/// unknown VALUE types and structured values like REQUEST-STATUS (3.8.8.3)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct ICalUnknown(pub String);
+
+impl ICalValueTrait for ICalUnknown {
+    fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
+        Ok(Self(value.to_string()))
+    }
+
+    fn serialize(&self) -> String {
+        self.0.clone()
+    }
+}
+
+impl From<&str> for ICalUnknown {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl std::ops::Deref for ICalUnknown {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{component::ICalComponent, values::{text::ICalText, unknown::ICalUnknown}};
+
+    #[test]
+    fn test_unknown_round_trip() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:a\\, b\\; c\r\nREQUEST-STATUS:2.0;Success\r\nX-LIST:a,b\r\nX-PATH:C:\\dir\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let mut vcal = ICalComponent::from_ics(ics).unwrap();
+        let vevent = vcal.expect_vevent();
+        assert_eq!(vevent.get_prop("SUMMARY").unwrap().get_as::<ICalText>().unwrap(), "a, b; c");
+        assert_eq!(vevent.get_prop("REQUEST-STATUS").unwrap().get_as::<ICalUnknown>().unwrap().0, "2.0;Success");
+        assert_eq!(vevent.get_prop("X-LIST").unwrap().get_as::<ICalUnknown>().unwrap().0, "a,b");
+        assert_eq!(vevent.get_prop("X-PATH").unwrap().get_as::<ICalUnknown>().unwrap().0, "C:\\dir");
+        assert_eq!(vcal.to_ics(), ics);
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Context;
+use url::Url;
+
+use crate::property::{ICalParameterMap, ICalProperty};
+use super::{ICalValue, ICalValueTrait};
+
+/// RFC 5545 3.3.13 URI (RFC 3986)
+/// NOTE: serialized in its normalized form (ex. "http://example.com/")
+pub type ICalUri = Url;
+
+impl ICalValueTrait for ICalUri {
+    fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
+        Url::parse(value).with_context(|| format!("Invalid URI {value}"))
+    }
+
+    fn serialize(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl From<Url> for ICalProperty {
+    fn from(value: Url) -> Self {
+        Self::from_value(ICalValue::Uri(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::uri::*;
+
+    #[test]
+    fn test_uri() {
+        let value = "http://example.com/pub/calendars/jsmith/mytime.ics?a=1;b=2,3";
+        let uri = <ICalUri as ICalValueTrait>::parse(value, &ICalParameterMap::new()).unwrap();
+        assert_eq!(uri.host_str(), Some("example.com"));
+        assert_eq!(uri.serialize(), value);
+        assert!(<ICalUri as ICalValueTrait>::parse("not a uri", &ICalParameterMap::new()).is_err());
+    }
+}
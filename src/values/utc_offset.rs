@@ -0,0 +1,99 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail};
+use chrono::FixedOffset;
+
+use crate::property::{ICalParameterMap, ICalProperty};
+use super::{ICalValue, ICalValueTrait};
+
+/// RFC 5545 3.3.14 UTC Offset
+/// Syntax: ("+" / "-") time-hour time-minute [time-second]
+/// Examples:
+///  "-0500" = 5 hours behind UTC
+///  "+013045" = 1 hour, 30 minutes, 45 seconds ahead of UTC
+pub type ICalUtcOffset = FixedOffset;
+
+impl ICalValueTrait for ICalUtcOffset {
+    fn parse(value: &str, _: &ICalParameterMap) -> anyhow::Result<Self> {
+        let (sign, digits) = match value.split_at_checked(1) {
+            Some(("+", digits)) => (1, digits),
+            Some(("-", digits)) => (-1, digits),
+            _ => bail!("UTC offset {value} missing sign"),
+        };
+        if !(digits.len() == 4 || digits.len() == 6) || !digits.chars().all(|c| c.is_ascii_digit()) {
+            bail!("Invalid UTC offset {value}");
+        }
+        let part = |i: usize| digits.get(i..i + 2).map_or(Ok(0), i32::from_str);
+        let (hours, minutes, seconds) = (part(0)?, part(2)?, part(4)?);
+        if minutes > 59 || seconds > 59 {
+            bail!("Invalid UTC offset {value}");
+        }
+        let seconds = hours * 3600 + minutes * 60 + seconds;
+        //"-0000" is not allowed
+        if sign < 0 && seconds == 0 {
+            bail!("Negative zero UTC offset {value}");
+        }
+        FixedOffset::east_opt(sign * seconds).ok_or(anyhow!("UTC offset {value} out of range"))
+    }
+
+    /// seconds are only written when non-zero
+    fn serialize(&self) -> String {
+        let seconds = self.local_minus_utc();
+        let sign = if seconds < 0 { '-' } else { '+' };
+        let seconds = seconds.abs();
+        let mut value = format!("{sign}{:02}{:02}", seconds / 3600, seconds / 60 % 60);
+        if seconds % 60 != 0 {
+            value.push_str(&format!("{:02}", seconds % 60));
+        }
+        value
+    }
+}
+
+impl From<FixedOffset> for ICalProperty {
+    fn from(value: FixedOffset) -> Self {
+        Self::from_value(ICalValue::UtcOffset(value))
+    }
+}
+
+/// FixedOffset has no serde impls, so UTC offsets are written as in ICalendar (ex. "-0500")
+#[cfg(feature = "serde")]
+pub(crate) mod serde_utc_offset {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::{property::ICalParameterMap, values::ICalValueTrait};
+    use super::ICalUtcOffset;
+
+    pub fn serialize<S: Serializer>(offset: &ICalUtcOffset, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&offset.serialize())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ICalUtcOffset, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        ICalUtcOffset::parse(&value, &ICalParameterMap::new()).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::values::utc_offset::*;
+
+    #[test]
+    fn test_utc_offset() {
+        assert_utc_offset("-0500", -5 * 3600);
+        assert_utc_offset("+013045", 5445);
+        assert_utc_offset("+0000", 0);
+    }
+
+    #[test]
+    fn test_utc_offset_invalid() {
+        for value in ["0100", "-0000", "-000000", "+0160", "+01", "+1:00"] {
+            assert!(ICalUtcOffset::parse(value, &ICalParameterMap::new()).is_err(), "{value} should be invalid");
+        }
+    }
+
+    fn assert_utc_offset(value: &str, expected: i32) {
+        let result = ICalUtcOffset::parse(value, &ICalParameterMap::new()).expect("Failed to parse!");
+        assert_eq!(result.local_minus_utc(), expected);
+        assert_eq!(result.serialize(), value);
+    }
+}
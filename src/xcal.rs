@@ -0,0 +1,280 @@
+use anyhow::{anyhow, bail};
+use minidom::Element;
+use serde_json::{Map, Value};
+
+use crate::component::ICalComponent;
+
+/// RFC 6321 3.2
+pub const XCAL_NAMESPACE: &str = "urn:ietf:params:xml:ns:icalendar-2.0";
+
+const ICALENDAR: &str = "icalendar";
+const PROPERTIES: &str = "properties";
+const COMPONENTS: &str = "components";
+const PARAMETERS: &str = "parameters";
+
+/// RFC 6321 3.6.10: recur parts are a sequence in this order
+const RECUR_PARTS: [&str; 14] = [
+    "freq", "until", "count", "interval", "bysecond", "byminute", "byhour",
+    "byday", "bymonthday", "byyearday", "byweekno", "bymonth", "bysetpos", "wkst",
+];
+
+// xCal (RFC 6321) and jCal (RFC 7265) describe the same structure,
+// so both directions convert through jCal
+
+impl ICalComponent {
+    /// RFC 6321: <icalendar><vcalendar><properties>...</properties><components>...</components></vcalendar></icalendar>
+    pub fn to_xcal(&self) -> String {
+        let icalendar = Element::builder(ICALENDAR, XCAL_NAMESPACE)
+            .append(component_to_xcal(&self.to_jcal()))
+            .build();
+        let mut xml = Vec::new();
+        icalendar.write_to_decl(&mut xml).expect("Writing to a Vec can't fail");
+        String::from_utf8(xml).expect("minidom writes UTF-8")
+    }
+
+    /// Parse the first component of an <icalendar> element (or a component element itself)
+    pub fn from_xcal(xml: &str) -> anyhow::Result<Self> {
+        let root: Element = xml.parse()?;
+        let comp = match root.name() {
+            ICALENDAR => root.children().next().ok_or(anyhow!("Empty <icalendar>"))?,
+            _ => &root,
+        };
+        Self::from_jcal(&component_to_jcal(comp)?)
+    }
+}
+
+fn element(name: &str, text: String) -> Element {
+    Element::builder(name, XCAL_NAMESPACE).append(text).build()
+}
+
+/// strings as they are, numbers and booleans in their JSON form
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn component_to_xcal(jcal: &Value) -> Element {
+    let name = jcal[0].as_str().unwrap_or_default();
+    let mut comp = Element::bare(name, XCAL_NAMESPACE);
+    let props: Vec<Element> = jcal[1].as_array().into_iter().flatten().map(property_to_xcal).collect();
+    if !props.is_empty() {
+        comp.append_child(Element::builder(PROPERTIES, XCAL_NAMESPACE).append_all(props).build());
+    }
+    let comps: Vec<Element> = jcal[2].as_array().into_iter().flatten().map(component_to_xcal).collect();
+    if !comps.is_empty() {
+        comp.append_child(Element::builder(COMPONENTS, XCAL_NAMESPACE).append_all(comps).build());
+    }
+    comp
+}
+
+/// RFC 6321 3.4: <name><parameters>...</parameters><type>value</type>...</name>
+fn property_to_xcal(jcal: &Value) -> Element {
+    let name = jcal[0].as_str().unwrap_or_default();
+    let kind = jcal[2].as_str().unwrap_or_default();
+    let mut prop = Element::bare(name, XCAL_NAMESPACE);
+
+    let params: Vec<Element> = jcal[1].as_object().into_iter().flatten().map(|(name, values)| {
+        let values = match values {
+            Value::Array(values) => values.iter().map(text).collect(),
+            value => vec![text(value)],
+        };
+        let kind = param_type(name);
+        Element::builder(name, XCAL_NAMESPACE)
+            .append_all(values.into_iter().map(|value| element(kind, value)))
+            .build()
+    }).collect();
+    if !params.is_empty() {
+        prop.append_child(Element::builder(PARAMETERS, XCAL_NAMESPACE).append_all(params).build());
+    }
+
+    for value in jcal.as_array().into_iter().flatten().skip(3) {
+        match (kind, value) {
+            //RFC 6321 3.4.1.2: GEO has no value type element
+            ("float", Value::Array(geo)) => {
+                for (part, value) in ["latitude", "longitude"].into_iter().zip(geo) {
+                    prop.append_child(element(part, text(value)));
+                }
+            },
+            //RFC 6321 3.4.1.3: REQUEST-STATUS
+            ("text", Value::Array(parts)) => {
+                for (part, value) in ["code", "description", "data"].into_iter().zip(parts) {
+                    prop.append_child(element(part, text(value)));
+                }
+            },
+            ("period", Value::Array(parts)) => {
+                let start = parts.first().map(text).unwrap_or_default();
+                let end = parts.get(1).map(text).unwrap_or_default();
+                let end_kind = if end.trim_start_matches(['+', '-']).starts_with('P') { "duration" } else { "end" };
+                prop.append_child(Element::builder(kind, XCAL_NAMESPACE)
+                    .append(element("start", start))
+                    .append(element(end_kind, end))
+                    .build());
+            },
+            ("recur", Value::Object(parts)) => {
+                let mut recur = Element::bare(kind, XCAL_NAMESPACE);
+                for (part, values) in RECUR_PARTS.into_iter().filter_map(|part| Some((part, parts.get(part)?))) {
+                    let values = match values {
+                        Value::Array(values) => values.iter().collect(),
+                        value => vec![value],
+                    };
+                    for value in values {
+                        recur.append_child(element(part, text(value)));
+                    }
+                }
+                prop.append_child(recur);
+            },
+            (kind, value) => { prop.append_child(element(kind, text(value))); },
+        }
+    }
+    prop
+}
+
+/// RFC 6321 3.5: parameter values are typed too
+fn param_type(name: &str) -> &'static str {
+    match name {
+        "altrep" | "dir" => "uri",
+        "delegated-from" | "delegated-to" | "member" | "sent-by" => "cal-address",
+        _ => "text",
+    }
+}
+
+fn component_to_jcal(comp: &Element) -> anyhow::Result<Value> {
+    let mut props = Vec::new();
+    let mut comps = Vec::new();
+    for child in comp.children() {
+        match child.name() {
+            PROPERTIES => props = child.children().map(property_to_jcal).collect::<anyhow::Result<_>>()?,
+            COMPONENTS => comps = child.children().map(component_to_jcal).collect::<anyhow::Result<_>>()?,
+            name => bail!("Unexpected <{name}> in <{}>", comp.name()),
+        }
+    }
+    Ok(Value::Array(vec![comp.name().into(), props.into(), comps.into()]))
+}
+
+fn property_to_jcal(prop: &Element) -> anyhow::Result<Value> {
+    let mut params = Map::new();
+    let mut kind = None;
+    let mut values = Vec::new();
+    for child in prop.children() {
+        match child.name() {
+            PARAMETERS => for param in child.children() {
+                let mut param_values: Vec<Value> = param.children().map(|value| value.text().into()).collect();
+                let value = match param_values.len() {
+                    1 => param_values.remove(0),
+                    _ => param_values.into(),
+                };
+                params.insert(param.name().to_string(), value);
+            },
+            "latitude" | "longitude" => {
+                kind = Some("float".to_string());
+                let value = child.text().trim().parse::<f64>()?;
+                match values.first_mut() {
+                    Some(Value::Array(geo)) => geo.push(value.into()),
+                    _ => values.push(vec![value].into()),
+                }
+            },
+            //RFC 6321 3.4.1.3: REQUEST-STATUS
+            "code" | "description" | "data" => {
+                kind = Some("text".to_string());
+                match values.first_mut() {
+                    Some(Value::Array(parts)) => parts.push(child.text().into()),
+                    _ => values.push(vec![child.text()].into()),
+                }
+            },
+            name => {
+                kind.get_or_insert_with(|| name.to_string());
+                values.push(value_to_jcal(child));
+            },
+        }
+    }
+    let kind = kind.ok_or(anyhow!("<{}> has no value", prop.name()))?;
+    let mut jcal = vec![prop.name().into(), Value::Object(params), kind.into()];
+    jcal.append(&mut values);
+    Ok(Value::Array(jcal))
+}
+
+fn value_to_jcal(value: &Element) -> Value {
+    match value.name() {
+        "period" => value.children().map(|part| part.text().into()).collect::<Vec<Value>>().into(),
+        "recur" => {
+            let mut parts = Map::new();
+            for part in value.children() {
+                let text: Value = part.text().into();
+                match parts.get_mut(part.name()) {
+                    Some(Value::Array(values)) => values.push(text),
+                    Some(first) => *first = vec![first.take(), text].into(),
+                    None => { parts.insert(part.name().to_string(), text); },
+                }
+            }
+            Value::Object(parts)
+        },
+        "boolean" => value.text().trim().eq_ignore_ascii_case("true").into(),
+        _ => value.text().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::ICalComponent;
+
+    #[test]
+    fn test_xcal() {
+        let ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:1\r\nDTSTART;TZID=Europe/Berlin:20240701T090000\r\nDTEND;VALUE=DATE:20240702\r\nRRULE:FREQ=WEEKLY;UNTIL=20241231T230000Z;BYDAY=MO,-1FR;BYMONTH=7\r\nRDATE;VALUE=PERIOD:20240801T090000/PT1H\r\nCATEGORIES:A,B\r\nATTENDEE;MEMBER=\"mailto:a@example.com\":mailto:jane@example.com\r\nSUMMARY:Fish & <Chips>\r\nREQUEST-STATUS:2.0;Success\r\nX-FLAG;VALUE=BOOLEAN:TRUE\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let vcal = ICalComponent::from_ics(ics).unwrap();
+        let xml = vcal.to_xcal();
+        for expected in [
+            "<icalendar xmlns='urn:ietf:params:xml:ns:icalendar-2.0'><vcalendar><properties><version><text>2.0</text></version></properties><components><vevent>",
+            "<dtstart><parameters><tzid><text>Europe/Berlin</text></tzid></parameters><date-time>2024-07-01T09:00:00</date-time></dtstart>",
+            "<dtend><date>2024-07-02</date></dtend>",
+            "<recur><freq>WEEKLY</freq><until>2024-12-31T23:00:00Z</until><byday>MO</byday><byday>-1FR</byday><bymonth>7</bymonth></recur>",
+            "<period><start>2024-08-01T09:00:00</start><duration>PT1H</duration></period>",
+            "<categories><text>A</text><text>B</text></categories>",
+            "<member><cal-address>mailto:a@example.com</cal-address></member>",
+            "<summary><text>Fish &amp; &lt;Chips&gt;</text></summary>",
+            "<request-status><code>2.0</code><description>Success</description></request-status>",
+            "<x-flag><boolean>true</boolean></x-flag>",
+        ] {
+            assert!(xml.contains(expected), "{expected} missing from {xml}");
+        }
+
+        let out = ICalComponent::from_xcal(&xml).unwrap();
+        assert_eq!(out.to_ics(), vcal.to_ics());
+    }
+
+    #[test]
+    fn test_from_xcal() {
+        //RFC 6321 Appendix B.1 with GEO and REQUEST-STATUS
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<icalendar xmlns="urn:ietf:params:xml:ns:icalendar-2.0">
+  <vcalendar>
+    <properties>
+      <prodid><text>-//Example Inc.//Example Calendar//EN</text></prodid>
+      <version><text>2.0</text></version>
+    </properties>
+    <components>
+      <vevent>
+        <properties>
+          <dtstamp><date-time>2008-02-05T19:12:24Z</date-time></dtstamp>
+          <dtstart><date>2008-10-06</date></dtstart>
+          <summary><text>Planning meeting</text></summary>
+          <uid><text>4088E990AD89CB3DBB484909</text></uid>
+          <geo><latitude>37.386013</latitude><longitude>-122.082932</longitude></geo>
+          <request-status><code>2.0</code><description>Success</description></request-status>
+        </properties>
+      </vevent>
+    </components>
+  </vcalendar>
+</icalendar>"#;
+        let mut vcal = ICalComponent::from_xcal(xml).unwrap();
+        let ics = vcal.to_ics();
+        assert!(ics.contains("\r\nDTSTART;VALUE=DATE:20081006\r\n"), "{ics}");
+        assert!(ics.contains("\r\nREQUEST-STATUS:2.0;Success\r\n"), "{ics}");
+        let vevent = vcal.expect_vevent();
+        assert_eq!(vevent.get_summary_value().unwrap(), "Planning meeting");
+        assert_eq!(vevent.get_geo_value().unwrap(), &(37.386013, -122.082932));
+
+        assert!(ICalComponent::from_xcal("<icalendar xmlns=\"urn:ietf:params:xml:ns:icalendar-2.0\"/>").is_err());
+    }
+}